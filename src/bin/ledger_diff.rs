@@ -0,0 +1,45 @@
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+
+use llm_nature_experiential::diff::diff_ledgers;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow};
+
+/// Compare two replay ledgers step by step, aligned by `t`.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Baseline replay ledger (A).
+    a: PathBuf,
+
+    /// Replay ledger to compare against A (B).
+    b: PathBuf,
+
+    /// Print JSON instead of the text report.
+    #[arg(long)]
+    json: bool,
+
+    /// Exit nonzero if any step's ignition decision flipped.
+    #[arg(long)]
+    fail_on_flip: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let a = LedgerReader::<_, ReplayRow>::open(&args.a)?.collect::<Result<Vec<_>>>()?;
+    let b = LedgerReader::<_, ReplayRow>::open(&args.b)?.collect::<Result<Vec<_>>>()?;
+    let diff = diff_ledgers(&a, &b);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", diff);
+    }
+    if args.fail_on_flip && !diff.flips.is_empty() {
+        anyhow::bail!(
+            "{} step(s) changed their ignition decision",
+            diff.flips.len()
+        );
+    }
+    Ok(())
+}
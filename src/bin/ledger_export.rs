@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use llm_nature_experiential::export::{write_columnar, write_csv, Format, Layout, Table};
+use llm_nature_experiential::ledger::{ledger_kind, LedgerReader, LedgerRow, ReplayRow, TraceRow};
+
+/// Flatten a trace or replay ledger into a CSV or columnar table.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Ledger to export.
+    ledger: PathBuf,
+
+    /// Output file (default: the ledger path with a .csv / .lncol extension), or `-` for stdout.
+    #[arg(short, long)]
+    out: Option<String>,
+
+    /// csv or columnar.
+    #[arg(short, long, default_value = "csv")]
+    format: Format,
+
+    /// wide (one line per row, `q_after_0..n`) or long (t, field, index, value).
+    #[arg(short, long, default_value = "wide")]
+    layout: Layout,
+
+    /// Ledger kind for headerless (v1) ledgers: trace or replay.
+    #[arg(long)]
+    kind: Option<String>,
+}
+
+fn load<T: LedgerRow + Serialize>(path: &Path, layout: Layout) -> Result<Table> {
+    let rows = LedgerReader::<_, T>::open(path)?.collect::<Result<Vec<T>>>()?;
+    Table::build(&rows, layout)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let kind = match (args.kind.clone(), ledger_kind(&args.ledger)?) {
+        (Some(k), _) | (None, Some(k)) => k,
+        (None, None) => anyhow::bail!(
+            "{} has no header; pass --kind trace|replay",
+            args.ledger.display()
+        ),
+    };
+    let table = match kind.as_str() {
+        "trace" => load::<TraceRow>(&args.ledger, args.layout)?,
+        "replay" => load::<ReplayRow>(&args.ledger, args.layout)?,
+        other => anyhow::bail!("unknown ledger kind {:?}", other),
+    };
+
+    let out = args.out.unwrap_or_else(|| {
+        args.ledger
+            .with_extension(args.format.extension())
+            .display()
+            .to_string()
+    });
+    let mut w: Box<dyn Write> = if out == "-" {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(BufWriter::new(
+            File::create(&out).with_context(|| format!("creating {}", out))?,
+        ))
+    };
+    match args.format {
+        Format::Csv => write_csv(&mut w, &table)?,
+        Format::Columnar => write_columnar(&mut w, &table)?,
+    }
+    w.flush()?;
+
+    if out != "-" {
+        eprintln!(
+            "Wrote {} ({} rows x {} columns)",
+            out,
+            table.n_rows(),
+            table.columns.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::path::PathBuf;
+
+use llm_nature_experiential::ledger::{ledger_kind, LedgerReader, ReplayRow, TraceRow};
+use llm_nature_experiential::summary::RunSummary;
+
+/// Ignition statistics for a run's trace and/or replay ledgers.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Ledgers to summarize (kind read from each header).
+    #[arg(default_values = ["out/trace_loop.ndjson", "out/replay_loop.ndjson"])]
+    ledgers: Vec<PathBuf>,
+
+    /// Print JSON instead of the text report.
+    #[arg(long)]
+    json: bool,
+
+    /// Also write the JSON summary to this file.
+    #[arg(long, value_name = "PATH")]
+    json_out: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut trace: Vec<TraceRow> = Vec::new();
+    let mut replay: Vec<ReplayRow> = Vec::new();
+    for path in &args.ledgers {
+        match ledger_kind(path)?.as_deref() {
+            Some("trace") => {
+                trace.extend(LedgerReader::<_, TraceRow>::open(path)?.collect::<Result<Vec<_>>>()?)
+            }
+            Some("replay") => replay
+                .extend(LedgerReader::<_, ReplayRow>::open(path)?.collect::<Result<Vec<_>>>()?),
+            Some(other) => anyhow::bail!("{}: unknown ledger kind {:?}", path.display(), other),
+            None => anyhow::bail!("{}: no ledger header", path.display()),
+        }
+    }
+
+    let summary = RunSummary::new(&trace, &replay);
+    let json = serde_json::to_string_pretty(&summary)?;
+    if let Some(p) = &args.json_out {
+        std::fs::write(p, format!("{}\n", json))
+            .with_context(|| format!("writing {}", p.display()))?;
+    }
+    if args.json {
+        println!("{}", json);
+    } else {
+        print!("{}", summary);
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use llm_nature_experiential::chain::verify_chain;
+
+/// Check the hash chain of ledgers written with `--hash-chain`.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Ledger files to verify.
+    #[arg(required = true)]
+    ledgers: Vec<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let mut failed = 0usize;
+    for path in &args.ledgers {
+        let report = File::open(path)
+            .with_context(|| format!("opening {}", path.display()))
+            .and_then(|f| verify_chain(BufReader::new(f)));
+        match report {
+            Ok(r) => println!(
+                "{}: OK ({} rows, config {}, head {})",
+                path.display(),
+                r.rows,
+                r.config_hash.as_deref().unwrap_or("-"),
+                r.last_hash
+            ),
+            Err(e) => {
+                println!("{}: FAILED: {:#}", path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} ledger(s) failed verification",
+            failed,
+            args.ledgers.len()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use clap::Parser;
+use std::path::PathBuf;
+
+use llm_nature_experiential::config::RunConfig;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow};
+use llm_nature_experiential::replay::{verify_link, verify_row, Divergence};
+
+/// Re-execute a replay ledger through the current kernel and report the first divergence.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Replay ledger to verify.
+    #[arg(short, long, default_value = "out/replay_loop.ndjson")]
+    replay: PathBuf,
+
+    /// Max absolute difference tolerated per value.
+    #[arg(long, default_value_t = 1e-9)]
+    tol: f64,
+
+    /// Run-config to use instead of the ledger header (required for headerless ledgers).
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Rows are independent scenarios (sniff_run output): skip q_next -> q_before checks.
+    #[arg(long)]
+    independent: bool,
+
+    /// Report every divergence instead of stopping at the first divergent step.
+    #[arg(long)]
+    all: bool,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let reader = LedgerReader::<_, ReplayRow>::open(&args.replay)?;
+    let cfg = match (&args.config, reader.header()) {
+        (Some(p), _) => RunConfig::load(p)?,
+        (None, Some(h)) => h.config.clone(),
+        (None, None) => anyhow::bail!("{} has no header; pass --config", args.replay.display()),
+    };
+
+    let mut prev: Option<ReplayRow> = None;
+    let mut steps = 0usize;
+    let mut divergent_steps = 0usize;
+    for row in reader {
+        let row = row?;
+        let mut found: Vec<Divergence> = verify_row(&cfg.kernel, &row, args.tol)?;
+        if let (false, Some(p)) = (args.independent, &prev) {
+            found.extend(verify_link(&cfg.kernel, p, &row, args.tol));
+        }
+        steps += 1;
+
+        if !found.is_empty() {
+            divergent_steps += 1;
+            if divergent_steps == 1 {
+                println!("First divergent step: t={}", row.t);
+            }
+            for d in &found {
+                println!("  {}", d);
+            }
+            if !args.all {
+                break;
+            }
+        }
+        prev = Some(row);
+    }
+
+    if divergent_steps > 0 {
+        anyhow::bail!("{} divergent step(s) in {} checked", divergent_steps, steps);
+    }
+    println!("OK: {} steps reproduced within {:e}", steps, args.tol);
+    Ok(())
+}
@@ -1,354 +1,258 @@
-use serde::Deserialize;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-use ndarray::{Array1, Array2};
-
-use llm_nature_experiential::adapter::{bayes_update, normalize};
-use llm_nature_experiential::broadcast::{apply_broadcast, expand_rg_to_n, rg_avg_pool};
-use llm_nature_experiential::ignition::{coherence, efficiency, Params};
-use llm_nature_experiential::ledger::{ndjson_write_row, ReplayRow, TraceRow};
-use llm_nature_experiential::memory::{MemoryRow, MemoryState};
-use llm_nature_experiential::policy::choose_action;
-use llm_nature_experiential::sensory::sensory_from_flat_col;
-use llm_nature_experiential::util::{ravel_multi_index, safe_ln_n};
-
-const EPS: f64 = 1e-9;
-
-#[derive(Debug, Clone, Deserialize)]
-#[allow(non_snake_case)]
-struct StreamEvent {
-    t: u64,
-    o: Vec<usize>,
-    A_shape: Vec<usize>,
-    #[serde(alias = "A_flat_col")]
-    a_flat_col: Vec<f64>,
-    #[allow(dead_code)]
-    tactile_flat_col: Option<Vec<f64>>,
-    p_prior: Vec<f64>,
-    task_vec: Vec<f64>,
-    q0: Option<Vec<f64>>,
-    sniff_strength: Option<f64>,
-    touch_pressure: Option<f64>,
-}
-
-#[derive(Clone, Debug)]
-struct Msg {
-    level: u8,
-    dx: Array1<f64>,
-    prec: Array1<f64>,
-    e: f64,
-    p: f64,
-    k: f64,
+use anyhow::Context;
+use clap::Parser;
+use std::path::PathBuf;
+
+use llm_nature_experiential::checkpoint::Checkpoint;
+use llm_nature_experiential::config::RunConfig;
+use llm_nature_experiential::event::EventReader;
+use llm_nature_experiential::kernel::Kernel;
+use llm_nature_experiential::ledger::{LedgerHeader, LedgerWriter, WriterOptions};
+use llm_nature_experiential::stream::{EmitKind, Endpoint, RowStream};
+use llm_nature_experiential::validate::{describe, ValidationMode, Validator, Verdict};
+
+/// Run the ignition kernel over a sniff stream and write trace/replay ledgers.
+#[derive(Debug, Parser)]
+struct Args {
+    /// NDJSON event stream: a file, `-` for stdin, or `unix:PATH` for a socket.
+    #[arg(short, long, default_value = "data/sniff_stream.ndjson")]
+    input: Endpoint,
+
+    /// Directory receiving trace_loop.ndjson and replay_loop.ndjson.
+    #[arg(short, long, default_value = "out")]
+    out_dir: PathBuf,
+
+    /// Run-config file (.json or .toml).
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Config override `key=value`, e.g. `params.gamma=0.5`; repeatable.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+
+    /// Hash-chain ledger rows (sets `hash_chain = true`).
+    #[arg(long)]
+    hash_chain: bool,
+
+    /// Flush ledgers every N rows (0: only at the end).
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    flush_every: usize,
+
+    /// fsync ledgers before moving them into place.
+    #[arg(long)]
+    fsync: bool,
+
+    /// Memory window size (shorthand for `--set mem_window=N`).
+    #[arg(long)]
+    mem_window: Option<usize>,
+
+    /// Action policy: heuristic, smooth, fixed, random or efe (shorthand for
+    /// `--set policy=NAME`).
+    #[arg(long, value_name = "NAME")]
+    policy: Option<String>,
+
+    /// Invalid events: fail (default), skip, or repair; overrides the config.
+    #[arg(long, value_name = "MODE")]
+    on_invalid: Option<ValidationMode>,
+
+    /// Validate config and run the stream without writing ledgers.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Save a checkpoint every N steps (0: never) and at the end of the run.
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    checkpoint_every: usize,
+
+    /// Checkpoint file (default: <out-dir>/checkpoint.json).
+    #[arg(long, value_name = "PATH")]
+    checkpoint: Option<PathBuf>,
+
+    /// Continue from the checkpoint: restore the kernel state, skip events up to
+    /// its last `t`, and append to the existing ledgers.
+    #[arg(long)]
+    resume: bool,
+
+    /// Also emit rows live, one flushed line per row: `-` for stdout,
+    /// `unix:PATH` for a socket, or a file.
+    #[arg(long, value_name = "DEST")]
+    stream_out: Option<Endpoint>,
+
+    /// Row kinds to stream: trace, replay, decision; repeatable
+    /// (default: trace and replay).
+    #[arg(long = "emit", value_name = "KIND")]
+    emit: Vec<EmitKind>,
+
+    /// Closed loop: after each step, send the action for `t + 1` on the
+    /// stream output before that observation is read.
+    #[arg(long, requires = "stream_out")]
+    closed_loop: bool,
 }
 
-fn entropy(q: &Array1<f64>) -> f64 {
-    let q = q.mapv(|x| x.max(EPS));
-    let q = &q / (q.sum().max(EPS));
-    -q.iter().map(|&x| x * x.ln()).sum::<f64>()
-}
-
-fn kl(q: &Array1<f64>, p: &Array1<f64>) -> f64 {
-    let q = q.mapv(|x| x.max(EPS));
-    let p = p.mapv(|x| x.max(EPS));
-    let q = &q / (q.sum().max(EPS));
-    let p = &p / (p.sum().max(EPS));
-    q.iter()
-        .zip(p.iter())
-        .map(|(&qi, &pi)| qi * (qi.ln() - pi.ln()))
-        .sum()
-}
-
-// G = KL(q||p) - Eq log p(o|s)
-fn vfe(q: &Array1<f64>, p_prior: &Array1<f64>, likelihood_col: &Array1<f64>) -> f64 {
-    let qn = normalize(q);
-    let pn = normalize(p_prior);
-    let lik = likelihood_col.mapv(|x| x.max(EPS));
-    let eloglik = qn
-        .iter()
-        .zip(lik.iter())
-        .map(|(&qi, &li)| qi * li.ln())
-        .sum::<f64>();
-    kl(&qn, &pn) - eloglik
-}
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
 
-fn precision_from_dx(dx: &Array1<f64>, task: &Array1<f64>, level: u8) -> Array1<f64> {
-    let mut prec = dx.mapv(|x| x.abs());
-    if level == 1 {
-        let l = prec.len().min(task.len());
-        for i in 0..l {
-            prec[i] += 0.1 * task[i];
-        }
+    let mut overrides = args.overrides.clone();
+    if let Some(w) = args.mem_window {
+        overrides.push(format!("mem_window={}", w));
     }
-    let norm = prec.iter().map(|x| x * x).sum::<f64>().sqrt().max(EPS);
-    prec.mapv(|x| x / norm)
-}
-
-fn msg_metrics(
-    q_before: &Array1<f64>,
-    q_after: &Array1<f64>,
-    task: &Array1<f64>,
-    level: u8,
-) -> Msg {
-    let qb = normalize(q_before);
-    let qa = normalize(q_after);
-    let dx = &qa - &qb;
-
-    let e = kl(&qa, &qb);
-    let p = entropy(&qb) - entropy(&qa);
-    let l2 = dx.iter().map(|x| x * x).sum::<f64>().sqrt();
-    let nnz = dx.iter().filter(|&&x| x.abs() > 1e-6).count() as f64;
-    let k = l2 + 0.5 * (nnz / (dx.len() as f64 + EPS));
-
-    let prec = precision_from_dx(&dx, task, level);
-    Msg {
-        level,
-        dx,
-        prec,
-        e,
-        p,
-        k,
+    if let Some(p) = &args.policy {
+        overrides.push(format!("policy={}", p));
     }
-}
-
-fn task_biased_belief(q: &Array1<f64>, task: &Array1<f64>, gain: f64) -> Array1<f64> {
-    let mut logits = q.mapv(|x| x.max(EPS).ln());
-    let l = logits.len().min(task.len());
-    for i in 0..l {
-        logits[i] += gain * task[i];
+    let mut cfg = RunConfig::resolve(args.config.as_deref(), &overrides)?;
+    if let Some(mode) = args.on_invalid {
+        cfg.on_invalid = mode;
+    }
+    if args.hash_chain {
+        cfg.hash_chain = true;
     }
-    let m = logits.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let ex = logits.mapv(|x| (x - m).exp());
-    &ex / (ex.sum().max(EPS))
-}
 
-fn main() -> anyhow::Result<()> {
-    let params = Params {
-        alpha: 0.10,
-        beta: 0.25,
-        gamma: 0.80,
-        c_crit: 0.70,
-        delta: 0.05,
+    let fin = args.input.open_input()?;
+    let mut live = match &args.stream_out {
+        Some(dest) => {
+            let kinds = if args.emit.is_empty() {
+                vec![EmitKind::Trace, EmitKind::Replay]
+            } else {
+                args.emit.clone()
+            };
+            Some(RowStream::new(dest.open_output()?, kinds))
+        }
+        None => None,
+    };
+    // Status lines go to stderr when stdout carries the row stream.
+    let stdout_live = args.stream_out.as_ref().is_some_and(Endpoint::is_stdio);
+
+    let checkpoint_path = args
+        .checkpoint
+        .clone()
+        .unwrap_or_else(|| args.out_dir.join("checkpoint.json"));
+    let resume_from = if args.resume {
+        let c = Checkpoint::load(&checkpoint_path)?;
+        c.check_config(&cfg)?;
+        Some(c)
+    } else {
+        None
     };
 
-    let rg_level = 1usize;
-    let rg_cost = 0.1f64;
-    let lambda_broadcast = 1.0f64;
-
-    let in_path = "data/sniff_stream.ndjson";
-    std::fs::create_dir_all("out")?;
-    let mut ftrace = File::create("out/trace_loop.ndjson")?;
-    let mut freplay = File::create("out/replay_loop.ndjson")?;
-
-    let fin = BufReader::new(File::open(in_path)?);
-
-    let mut q_state: Option<Array1<f64>> = None;
-    let mut mem = MemoryState::new(64);
+    let trace_path = args.out_dir.join("trace_loop.ndjson");
+    let replay_path = args.out_dir.join("replay_loop.ndjson");
+    let mut sinks = if args.dry_run {
+        None
+    } else {
+        std::fs::create_dir_all(&args.out_dir)?;
+        let opts = WriterOptions {
+            flush_every: args.flush_every,
+            fsync: args.fsync,
+        };
+        let pair = match &resume_from {
+            Some(c) => (
+                LedgerWriter::resume(&trace_path, &cfg, c.steps, opts)?,
+                LedgerWriter::resume(&replay_path, &cfg, c.steps, opts)?,
+            ),
+            None => (
+                LedgerWriter::create_with(&trace_path, &LedgerHeader::new("trace", &cfg), opts)?,
+                LedgerWriter::create_with(&replay_path, &LedgerHeader::new("replay", &cfg), opts)?,
+            ),
+        };
+        Some(pair)
+    };
 
-    for line in fin.lines() {
-        let line = line?;
-        if line.trim().is_empty() {
+    let mut kernel = Kernel::from_config(cfg.kernel.clone())?;
+    let mut validator = Validator::new(cfg.on_invalid, kernel.a_tensor.is_some());
+    let mut steps = 0usize;
+    if let Some(c) = &resume_from {
+        c.restore(&mut kernel)?;
+        validator.prev_t = c.last_t;
+        validator.n_states = kernel.q_state.as_ref().map(|q| q.len());
+        steps = c.steps;
+    }
+    let resume_t = resume_from.as_ref().and_then(|c| c.last_t);
+
+    for item in EventReader::<_>::new(fin) {
+        let (line_no, ev) = match item {
+            Ok(x) => x,
+            Err(e) => {
+                let msg = format!("{:#}", e);
+                validator.reject(e)?;
+                eprintln!("skipped {}", msg);
+                continue;
+            }
+        };
+        if resume_t.is_some_and(|t| ev.t <= t) {
             continue;
         }
-        let ev: StreamEvent = serde_json::from_str(&line)?;
-
-        let n = ev.p_prior.len();
-        if ev.A_shape.is_empty() || ev.A_shape[0] != n {
-            anyhow::bail!(
-                "A_shape mismatch: expected first dim {}, got {:?}",
-                n,
-                ev.A_shape
-            );
-        }
-
-        let o_shape = &ev.A_shape[1..];
-        let o_idx = ravel_multi_index(&ev.o, o_shape);
-
-        let p_prior = Array1::from(ev.p_prior);
-        let task = Array1::from(ev.task_vec);
-
-        if q_state.is_none() {
-            let q0 = ev.q0.clone().unwrap_or_else(|| p_prior.to_vec());
-            q_state = Some(Array1::from(q0));
-        }
-        let q_before = q_state.as_ref().unwrap().clone();
-
-        let mem_feat_pre = mem.features(ev.t);
-
-        let (sniff_strength, touch_pressure, action_source) =
-            match (ev.sniff_strength, ev.touch_pressure) {
-                (Some(s), Some(tp)) => (s, tp, "event".to_string()),
-                _ => {
-                    let a = choose_action(&q_before, &mem_feat_pre, &task);
-                    (a.sniff_strength, a.touch_pressure, "policy".to_string())
-                }
-            };
-
-        let sensory = sensory_from_flat_col(ev.a_flat_col, sniff_strength, touch_pressure);
-        let lik_col = Array1::from(sensory.lik_mod.clone());
-
-        let u_t = entropy(&normalize(&q_before)) / safe_ln_n(n);
-        let g_before = vfe(&q_before, &p_prior, &lik_col);
-
-        let q_after = bayes_update(&normalize(&p_prior), &normalize(&lik_col));
-        let g_after_local = vfe(&q_after, &p_prior, &lik_col);
-
-        let q_task = task_biased_belief(&q_after, &task, 0.05);
-
-        let m0 = msg_metrics(&q_before, &q_after, &task, 0);
-        let m1 = msg_metrics(&q_after, &q_task, &task, 1);
-
-        let theta = params.alpha + params.beta * u_t;
-
-        let mut survivors: Vec<Msg> = Vec::new();
-        for m in [m0, m1] {
-            let eta = efficiency(m.e, m.p, m.k);
-            if eta < theta {
-                continue;
+        let ev = match validator.admit(line_no, ev)? {
+            Verdict::Accept(ev) => ev,
+            Verdict::Repaired(ev, issues) => {
+                eprintln!("repaired line {}: {}", line_no, describe(&issues));
+                ev
             }
-            let dx_rg = rg_avg_pool(&m.dx, rg_level);
-            let k_rg = m.k + rg_cost;
-            let eta_rg = efficiency(m.e, m.p, k_rg);
-            if eta_rg >= params.gamma * theta {
-                survivors.push(Msg { dx: dx_rg, ..m });
+            Verdict::Skipped(issues) => {
+                eprintln!("skipped line {}: {}", line_no, describe(&issues));
+                continue;
             }
-        }
-
-        let survivors_n = survivors.len();
-        let survivor_levels: Vec<u8> = survivors.iter().map(|m| m.level).collect();
-
-        let coh = if survivors_n == 0 {
-            0.0
-        } else {
-            let d = survivors[0].prec.len();
-            let mut mat: Vec<f64> = Vec::with_capacity(survivors_n * d);
-            for m in &survivors {
-                mat.extend_from_slice(m.prec.as_slice().unwrap());
+        };
+        // The kernel state is untouched when a step fails, so skip and repair
+        // modes can drop the event like any other invalid line.
+        let out = match kernel
+            .step(&ev)
+            .with_context(|| format!("line {}", line_no))
+        {
+            Ok(out) => out,
+            Err(e) => {
+                let msg = format!("{:#}", e);
+                validator.reject_admitted(e)?;
+                eprintln!("skipped {}", msg);
+                continue;
             }
-            let precisions = Array2::from_shape_vec((survivors_n, d), mat).unwrap();
-            coherence(&precisions)
         };
-
-        let broadcast = if survivors_n == 0 {
-            Array1::from_vec(vec![])
-        } else {
-            let mut etas: Vec<f64> = survivors
-                .iter()
-                .map(|m| efficiency(m.e, m.p, m.k))
-                .collect();
-            let max_eta = etas.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-            for x in etas.iter_mut() {
-                *x = (*x - max_eta).exp();
+        steps += 1;
+        if let Some(live) = live.as_mut() {
+            live.emit(&out)?;
+            if args.closed_loop {
+                if let Some(a) = kernel.plan_action(ev.t + 1, &ev.task_vec) {
+                    live.emit_action(ev.t + 1, &a)?;
+                }
             }
-            let z = etas.iter().sum::<f64>().max(EPS);
-
-            let mut b = Array1::from_vec(vec![0.0; survivors[0].dx.len()]);
-            for (w_raw, m) in etas.iter().zip(survivors.iter()) {
-                let w = *w_raw / z;
-                b = b + &(m.dx.mapv(|x| x * w));
+        }
+        if let Some((ftrace, freplay)) = sinks.as_mut() {
+            ftrace.write_row(&out.trace)?;
+            freplay.write_row(&out.replay)?;
+            // Ledgers are synced first so they never hold fewer rows than the checkpoint.
+            if args.checkpoint_every > 0 && steps.is_multiple_of(args.checkpoint_every) {
+                ftrace.sync()?;
+                freplay.sync()?;
+                Checkpoint::capture(&cfg, &kernel, validator.prev_t, steps)?
+                    .save(&checkpoint_path)?;
             }
-            b
-        };
-
-        let b_expanded = expand_rg_to_n(&broadcast, n, rg_level);
-        let q_broadcast = apply_broadcast(&q_after, &b_expanded, lambda_broadcast);
-        let g_after_broadcast = vfe(&q_broadcast, &p_prior, &lik_col);
-
-        let d_g_local = g_before - g_after_local;
-        let d_g_broadcast = g_before - g_after_broadcast;
-
-        let ignite_reason = if survivors_n == 0 {
-            "no_survivors"
-        } else if coh < params.c_crit {
-            "coherence_fail"
-        } else if d_g_broadcast < params.delta {
-            "deltaG_fail"
-        } else {
-            "ignite"
         }
-        .to_string();
-
-        let ignited = ignite_reason == "ignite";
-
-        let q_next = if ignited {
-            q_broadcast.clone()
-        } else {
-            q_after.clone()
-        };
-        q_state = Some(q_next.clone());
-
-        let (dx0, e0, p0, k0, eta0) = if survivors_n == 0 {
-            (Array1::from_vec(vec![]), 0.0, 0.0, 0.0, 0.0)
-        } else {
-            let m = &survivors[0];
-            let eta0 = efficiency(m.e, m.p, m.k);
-            (m.dx.clone(), m.e, m.p, m.k, eta0)
-        };
-
-        let trace = TraceRow {
-            t: ev.t,
-            o_idx,
-            u_t,
-            g_before,
-            g_after_local,
-            g_after_broadcast,
-            d_g_local,
-            d_g_broadcast,
-            ignited,
-            ignite_reason: ignite_reason.clone(),
-            theta,
-            survivors_n,
-            coherence: coh,
-            survivor_levels: survivor_levels.clone(),
-            q_after: q_after.to_vec(),
-            broadcast: broadcast.to_vec(),
-            dx: dx0.to_vec(),
-            e: e0,
-            p: p0,
-            k: k0,
-            eta: eta0,
-        };
-        ndjson_write_row(&mut ftrace, &trace)?;
-
-        mem.push(MemoryRow {
-            t: ev.t,
-            ignited,
-            d_g_broadcast,
-            temperature: sensory.temperature,
-            sniff_strength,
-            touch_pressure,
-        });
-        let mem_feat_post = mem.features(ev.t);
+    }
 
-        let replay = ReplayRow {
-            t: ev.t,
-            o_idx,
-            sniff_strength,
-            touch_pressure,
-            action_source,
-            temperature: sensory.temperature,
-            q_before: q_before.to_vec(),
-            q_after: q_after.to_vec(),
-            q_broadcast: q_broadcast.to_vec(),
-            q_next: q_next.to_vec(),
-            survivors_n,
-            survivor_levels,
-            broadcast: broadcast.to_vec(),
-            b_expanded: b_expanded.to_vec(),
-            ignited,
-            ignite_reason,
-            g_before,
-            g_after_local,
-            g_after_broadcast,
-            d_g_local,
-            d_g_broadcast,
-            mem_window_len: mem_feat_post.window_len,
-            mem_ignite_rate: mem_feat_post.ignite_rate,
-            mem_mean_d_g_broadcast: mem_feat_post.mean_d_g_broadcast,
-        };
-        ndjson_write_row(&mut freplay, &replay)?;
+    if let Some((ftrace, freplay)) = sinks {
+        ftrace.finish()?;
+        freplay.finish()?;
+        if args.checkpoint_every > 0 || args.resume {
+            Checkpoint::capture(&cfg, &kernel, validator.prev_t, steps)?.save(&checkpoint_path)?;
+        }
     }
 
-    println!("Wrote out/trace_loop.ndjson and out/replay_loop.ndjson");
+    if validator.n_skipped + validator.n_repaired > 0 {
+        eprintln!(
+            "{} lines skipped, {} repaired",
+            validator.n_skipped, validator.n_repaired
+        );
+    }
+    let status = if args.dry_run {
+        format!("Dry run OK: {} events", steps)
+    } else {
+        format!(
+            "Wrote {} and {}",
+            trace_path.display(),
+            replay_path.display()
+        )
+    };
+    if stdout_live {
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
     Ok(())
 }
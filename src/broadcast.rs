@@ -21,7 +21,7 @@ pub fn rg_avg_pool(v: &Array1<f64>, rg_level: usize) -> Array1<f64> {
 }
 
 pub fn expand_rg_to_n(b_rg: &Array1<f64>, n: usize, rg_level: usize) -> Array1<f64> {
-    if b_rg.len() == 0 {
+    if b_rg.is_empty() {
         return Array1::from_vec(vec![0.0; n]);
     }
     let k = 1usize << rg_level;
@@ -33,7 +33,7 @@ pub fn expand_rg_to_n(b_rg: &Array1<f64>, n: usize, rg_level: usize) -> Array1<f
     }
     out.truncate(n);
     if out.len() < n {
-        out.extend(std::iter::repeat(0.0).take(n - out.len()));
+        out.extend(std::iter::repeat_n(0.0, n - out.len()));
     }
     Array1::from_vec(out)
 }
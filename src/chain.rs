@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::BufRead;
+
+use crate::ledger::LedgerTrailer;
+
+// prev_hash of the first line (the header) of a chained ledger.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Canonical serialization: compact JSON with object keys sorted.
+pub fn canonical_json(v: &Value) -> String {
+    match v {
+        Value::Object(m) => {
+            let mut keys: Vec<&String> = m.keys().collect();
+            keys.sort();
+            let body: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{}:{}", Value::String(k.clone()), canonical_json(&m[k])))
+                .collect();
+            format!("{{{}}}", body.join(","))
+        }
+        Value::Array(a) => {
+            let body: Vec<String> = a.iter().map(canonical_json).collect();
+            format!("[{}]", body.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+// Hash of a row's canonical form, `hash` field excluded.
+pub fn row_hash(v: &Value) -> String {
+    let mut v = v.clone();
+    if let Value::Object(m) = &mut v {
+        m.remove("hash");
+    }
+    sha256_hex(canonical_json(&v).as_bytes())
+}
+
+pub fn config_hash<T: Serialize>(config: &T) -> Result<String> {
+    Ok(sha256_hex(
+        canonical_json(&serde_json::to_value(config)?).as_bytes(),
+    ))
+}
+
+// Links rows: each gets `prev_hash` (the previous row's hash) and its own `hash`.
+#[derive(Clone, Debug)]
+pub struct HashChain {
+    prev: String,
+}
+
+impl Default for HashChain {
+    fn default() -> Self {
+        Self {
+            prev: GENESIS_HASH.to_string(),
+        }
+    }
+}
+
+impl HashChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Continue a chain whose last sealed row has hash `head`.
+    pub fn from_head(head: &str) -> Self {
+        Self {
+            prev: head.to_string(),
+        }
+    }
+
+    pub fn last_hash(&self) -> &str {
+        &self.prev
+    }
+
+    pub fn seal<T: Serialize>(&mut self, row: &T) -> Result<Value> {
+        let mut v = serde_json::to_value(row)?;
+        let m = v
+            .as_object_mut()
+            .ok_or_else(|| anyhow::anyhow!("only JSON objects can be chained"))?;
+        m.insert("prev_hash".to_string(), Value::String(self.prev.clone()));
+        let h = row_hash(&v);
+        v.as_object_mut()
+            .unwrap()
+            .insert("hash".to_string(), Value::String(h.clone()));
+        self.prev = h;
+        Ok(v)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChainReport {
+    // Data rows: header and trailer excluded.
+    pub rows: usize,
+    pub config_hash: Option<String>,
+    pub last_hash: String,
+}
+
+// Check every line's hash, its link to the previous line, and the header's
+// config_hash, and require the sealed trailer as the last line with the right
+// row count and head hash, so dropped trailing rows are caught too. Errors name
+// the first bad line.
+pub fn verify_chain<R: BufRead>(reader: R) -> Result<ChainReport> {
+    let mut prev = GENESIS_HASH.to_string();
+    let mut rows = 0usize;
+    let mut cfg_hash = None;
+    let mut last_line = 0usize;
+    let mut trailer_line = None;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.with_context(|| format!("line {}", line_no))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(at) = trailer_line {
+            anyhow::bail!("line {}: follows the trailer on line {}", line_no, at);
+        }
+        last_line = line_no;
+        let v: Value = serde_json::from_str(&line)
+            .with_context(|| format!("line {}: not valid JSON (truncated?)", line_no))?;
+
+        let got_prev = v.get("prev_hash").and_then(Value::as_str);
+        let got_hash = v.get("hash").and_then(Value::as_str);
+        let (got_prev, got_hash) = match (got_prev, got_hash) {
+            (Some(p), Some(h)) => (p, h),
+            _ => anyhow::bail!("line {}: row is not hash-chained", line_no),
+        };
+        if got_prev != prev {
+            anyhow::bail!(
+                "line {}: prev_hash {} does not match previous row hash {}",
+                line_no,
+                got_prev,
+                prev
+            );
+        }
+        let expected = row_hash(&v);
+        if got_hash != expected {
+            anyhow::bail!(
+                "line {}: content hash mismatch (recorded {}, computed {})",
+                line_no,
+                got_hash,
+                expected
+            );
+        }
+
+        if let Some(cfg) = v.get("config") {
+            let recorded = v.get("config_hash").and_then(Value::as_str);
+            let computed = sha256_hex(canonical_json(cfg).as_bytes());
+            if recorded != Some(computed.as_str()) {
+                anyhow::bail!("line {}: config_hash does not match config", line_no);
+            }
+            cfg_hash = Some(computed);
+        } else if let Some(t) = LedgerTrailer::parse(&line) {
+            if t.rows != rows || t.head_hash != prev {
+                anyhow::bail!(
+                    "line {}: trailer seals {} rows ending at {}, found {} ending at {}",
+                    line_no,
+                    t.rows,
+                    t.head_hash,
+                    rows,
+                    prev
+                );
+            }
+            trailer_line = Some(line_no);
+        } else {
+            rows += 1;
+        }
+
+        prev = got_hash.to_string();
+    }
+
+    if trailer_line.is_none() {
+        anyhow::bail!(
+            "no trailer after line {}: rows were dropped or the ledger was never finished",
+            last_line
+        );
+    }
+    Ok(ChainReport {
+        rows,
+        config_hash: cfg_hash,
+        last_hash: prev,
+    })
+}
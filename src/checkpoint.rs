@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::chain::config_hash;
+use crate::config::RunConfig;
+use crate::kernel::{Kernel, PlannedAction};
+use crate::memory::{DecaySums, MemoryRow};
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+// Everything `sniff_loop` needs to continue a stream: the kernel's carried
+// belief and memory window, the last accepted `t`, and how many rows each
+// ledger held when the checkpoint was taken.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub config_hash: String,
+    pub last_t: Option<u64>,
+    pub steps: usize,
+    pub q_state: Option<Vec<f64>>,
+    pub mem_rows: Vec<MemoryRow>,
+    // Decay-mode memory sums (the rows alone do not determine them).
+    #[serde(default)]
+    pub mem_decay: Option<DecaySums>,
+    // Closed-loop action already announced for the next event.
+    #[serde(default)]
+    pub planned: Option<PlannedAction>,
+    // Latest event's columns and prior, the EFE policy's evidence.
+    #[serde(default)]
+    pub last_lik: Option<Vec<f64>>,
+    #[serde(default)]
+    pub last_tact: Option<Vec<f64>>,
+    #[serde(default)]
+    pub last_prior: Option<Vec<f64>>,
+    // `Policy::state` of stateful policies (the random policy's generator).
+    #[serde(default)]
+    pub policy_state: Option<serde_json::Value>,
+}
+
+impl Checkpoint {
+    pub fn capture(
+        cfg: &RunConfig,
+        kernel: &Kernel,
+        last_t: Option<u64>,
+        steps: usize,
+    ) -> Result<Self> {
+        Ok(Self {
+            version: CHECKPOINT_VERSION,
+            config_hash: config_hash(cfg)?,
+            last_t,
+            steps,
+            q_state: kernel.q_state.as_ref().map(|q| q.to_vec()),
+            mem_rows: kernel.mem.rows().iter().cloned().collect(),
+            mem_decay: kernel.mem.decay.clone(),
+            planned: kernel.planned.clone(),
+            last_lik: kernel.last_lik.clone(),
+            last_tact: kernel.last_tact.clone(),
+            last_prior: kernel.last_prior.clone(),
+            policy_state: kernel.policy.state(),
+        })
+    }
+
+    // The run-config must hash to the one the checkpoint was taken under.
+    pub fn check_config(&self, cfg: &RunConfig) -> Result<()> {
+        if self.version != CHECKPOINT_VERSION {
+            anyhow::bail!(
+                "unsupported checkpoint version {} (expected {})",
+                self.version,
+                CHECKPOINT_VERSION
+            );
+        }
+        if config_hash(cfg)? != self.config_hash {
+            anyhow::bail!("run-config differs from the one the checkpoint was taken with");
+        }
+        Ok(())
+    }
+
+    pub fn restore(&self, kernel: &mut Kernel) -> Result<()> {
+        kernel.q_state = self.q_state.clone().map(Array1::from);
+        kernel.mem.clear();
+        for row in &self.mem_rows {
+            kernel.mem.push(row.clone());
+        }
+        if self.mem_decay.is_some() {
+            kernel.mem.decay = self.mem_decay.clone();
+        }
+        kernel.planned = self.planned.clone();
+        kernel.last_lik = self.last_lik.clone();
+        kernel.last_tact = self.last_tact.clone();
+        kernel.last_prior = self.last_prior.clone();
+        if let Some(state) = &self.policy_state {
+            kernel.policy.restore(state)?;
+        }
+        Ok(())
+    }
+
+    // Written to a temporary file and renamed, so a crash mid-save keeps the
+    // previous checkpoint.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let text = serde_json::to_string(self)?;
+        std::fs::write(&tmp, text).with_context(|| format!("writing {:?}", tmp))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("renaming {:?}", tmp))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading checkpoint {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("parsing checkpoint {}", path.display()))
+    }
+}
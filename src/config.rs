@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::kernel::KernelConfig;
+use crate::validate::ValidationMode;
+
+pub const CONFIG_VERSION: u32 = 1;
+
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+// Versioned run-config; loaded from JSON or TOML and echoed into ledger headers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunConfig {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub kernel: KernelConfig,
+    // Handling of events that fail schema validation.
+    #[serde(default)]
+    pub on_invalid: ValidationMode,
+    // Seal ledger rows into a sha256 hash chain (see `chain`).
+    #[serde(default)]
+    pub hash_chain: bool,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            kernel: KernelConfig::default(),
+            on_invalid: ValidationMode::default(),
+            hash_chain: false,
+        }
+    }
+}
+
+impl RunConfig {
+    // Format is picked from the extension: `.toml` is TOML, anything else is JSON.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        let is_toml = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("toml"))
+            .unwrap_or(false);
+        let cfg: RunConfig = if is_toml {
+            toml::from_str(&text).with_context(|| format!("parsing TOML {}", path.display()))?
+        } else {
+            serde_json::from_str(&text)
+                .with_context(|| format!("parsing JSON {}", path.display()))?
+        };
+        cfg.validate()
+            .with_context(|| format!("invalid config {}", path.display()))?;
+        Ok(cfg)
+    }
+
+    // Config file (or defaults) plus `key=value` overrides, validated.
+    pub fn resolve(path: Option<&Path>, overrides: &[String]) -> Result<Self> {
+        let mut cfg = match path {
+            Some(p) => RunConfig::load(p)?,
+            None => RunConfig::default(),
+        };
+        for spec in overrides {
+            cfg.apply_override(spec)?;
+        }
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.version != CONFIG_VERSION {
+            anyhow::bail!(
+                "unsupported config version {} (expected {})",
+                self.version,
+                CONFIG_VERSION
+            );
+        }
+        self.kernel.validate()
+    }
+
+    // Apply a `key=value` override such as `params.gamma=0.5` or `rg_level=2`.
+    // Keys address fields of the kernel section (a leading `kernel.` is accepted);
+    // values are parsed as JSON, falling back to a bare string (`filter=sequential`).
+    // The result is not validated here.
+    pub fn apply_override(&mut self, spec: &str) -> Result<()> {
+        let (key, raw) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("override {:?} is not of the form key=value", spec))?;
+        let key = key.trim();
+        let path = key.strip_prefix("kernel.").unwrap_or(key);
+
+        let mut root = serde_json::to_value(&self.kernel)?;
+        let mut slot = &mut root;
+        for part in path.split('.') {
+            slot = slot
+                .get_mut(part)
+                .ok_or_else(|| anyhow!("unknown config key {:?}", key))?;
+        }
+        let raw = raw.trim();
+        *slot = serde_json::from_str(raw)
+            .unwrap_or_else(|_| serde_json::Value::String(raw.to_string()));
+
+        self.kernel = serde_json::from_value(root)
+            .with_context(|| format!("override {:?} has the wrong type", spec))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::kernel::IGNITE_REASONS;
+use crate::ledger::ReplayRow;
+use crate::summary::RunSummary;
+
+// Step whose ignition decision differs between the two runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Flip {
+    pub t: u64,
+    pub ignited_a: bool,
+    pub ignited_b: bool,
+    pub reason_a: String,
+    pub reason_b: String,
+}
+
+// Largest |a - b| of one quantity over all aligned steps.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaxDev {
+    pub field: String,
+    pub t: u64,
+    pub a: f64,
+    pub b: f64,
+    pub abs_diff: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shift {
+    pub a: f64,
+    pub b: f64,
+    pub shift: f64,
+}
+
+impl Shift {
+    fn new(a: f64, b: f64) -> Self {
+        Self { a, b, shift: b - a }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerDiff {
+    pub aligned: usize,
+    pub only_a: Vec<u64>,
+    pub only_b: Vec<u64>,
+    pub flips: Vec<Flip>,
+    // Steps whose q_next vectors differ in length (skipped for q_next deviation).
+    pub shape_mismatch: Vec<u64>,
+    // q_next first, then the free-energy fields; absent if nothing aligned.
+    pub max_dev: Vec<MaxDev>,
+    pub ignition_rate: Option<Shift>,
+    pub mean_d_g_local: Option<Shift>,
+    pub mean_d_g_broadcast: Option<Shift>,
+    pub ignite_reasons: BTreeMap<String, Shift>,
+}
+
+const FREE_ENERGY_FIELDS: [&str; 5] = [
+    "g_before",
+    "g_after_local",
+    "g_after_broadcast",
+    "d_g_local",
+    "d_g_broadcast",
+];
+
+fn free_energy(r: &ReplayRow, field: &str) -> f64 {
+    match field {
+        "g_before" => r.g_before,
+        "g_after_local" => r.g_after_local,
+        "g_after_broadcast" => r.g_after_broadcast,
+        "d_g_local" => r.d_g_local,
+        _ => r.d_g_broadcast,
+    }
+}
+
+fn track(slot: &mut Option<MaxDev>, field: &str, t: u64, a: f64, b: f64) {
+    let d = (a - b).abs();
+    if slot.as_ref().is_none_or(|m| d > m.abs_diff) {
+        *slot = Some(MaxDev {
+            field: field.to_string(),
+            t,
+            a,
+            b,
+            abs_diff: d,
+        });
+    }
+}
+
+// Aligns rows by `t`. Aggregate shifts compare the aligned steps only, so runs
+// over different stretches of a stream are not compared wholesale.
+pub fn diff_ledgers(a: &[ReplayRow], b: &[ReplayRow]) -> LedgerDiff {
+    let by_t: BTreeMap<u64, &ReplayRow> = b.iter().map(|r| (r.t, r)).collect();
+    let ts_a: BTreeSet<u64> = a.iter().map(|r| r.t).collect();
+
+    let mut d = LedgerDiff {
+        only_b: b
+            .iter()
+            .map(|r| r.t)
+            .filter(|t| !ts_a.contains(t))
+            .collect(),
+        ..Default::default()
+    };
+    let mut q_dev: Option<MaxDev> = None;
+    let mut fe_dev: Vec<Option<MaxDev>> = vec![None; FREE_ENERGY_FIELDS.len()];
+    let mut aligned_a = Vec::new();
+    let mut aligned_b = Vec::new();
+
+    for ra in a {
+        let Some(rb) = by_t.get(&ra.t) else {
+            d.only_a.push(ra.t);
+            continue;
+        };
+        d.aligned += 1;
+        aligned_a.push(ra.clone());
+        aligned_b.push((*rb).clone());
+
+        if ra.ignited != rb.ignited || ra.ignite_reason != rb.ignite_reason {
+            d.flips.push(Flip {
+                t: ra.t,
+                ignited_a: ra.ignited,
+                ignited_b: rb.ignited,
+                reason_a: ra.ignite_reason.clone(),
+                reason_b: rb.ignite_reason.clone(),
+            });
+        }
+        if ra.q_next.len() != rb.q_next.len() {
+            d.shape_mismatch.push(ra.t);
+        } else {
+            for (x, y) in ra.q_next.iter().zip(&rb.q_next) {
+                track(&mut q_dev, "q_next", ra.t, *x, *y);
+            }
+        }
+        for (slot, field) in fe_dev.iter_mut().zip(FREE_ENERGY_FIELDS) {
+            track(
+                slot,
+                field,
+                ra.t,
+                free_energy(ra, field),
+                free_energy(rb, field),
+            );
+        }
+    }
+    d.max_dev = std::iter::once(q_dev).chain(fe_dev).flatten().collect();
+
+    if d.aligned > 0 {
+        let sa = RunSummary::new(&[], &aligned_a);
+        let sb = RunSummary::new(&[], &aligned_b);
+        d.ignition_rate = Some(Shift::new(sa.ignition_rate, sb.ignition_rate));
+        d.mean_d_g_local = sa
+            .d_g_local
+            .zip(sb.d_g_local)
+            .map(|(x, y)| Shift::new(x.mean, y.mean));
+        d.mean_d_g_broadcast = sa
+            .d_g_broadcast
+            .zip(sb.d_g_broadcast)
+            .map(|(x, y)| Shift::new(x.mean, y.mean));
+        let reasons: BTreeSet<&String> = sa
+            .ignite_reasons
+            .keys()
+            .chain(sb.ignite_reasons.keys())
+            .collect();
+        for r in reasons {
+            let na = *sa.ignite_reasons.get(r).unwrap_or(&0) as f64;
+            let nb = *sb.ignite_reasons.get(r).unwrap_or(&0) as f64;
+            d.ignite_reasons.insert(r.clone(), Shift::new(na, nb));
+        }
+    }
+    d
+}
+
+impl fmt::Display for LedgerDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "aligned steps   {} (only in A: {}, only in B: {})",
+            self.aligned,
+            self.only_a.len(),
+            self.only_b.len()
+        )?;
+        writeln!(f, "ignition flips  {}", self.flips.len())?;
+        for fl in &self.flips {
+            writeln!(f, "  t={:<6} {} -> {}", fl.t, fl.reason_a, fl.reason_b)?;
+        }
+        if !self.shape_mismatch.is_empty() {
+            writeln!(f, "q_next length differs at t={:?}", self.shape_mismatch)?;
+        }
+        if !self.max_dev.is_empty() {
+            writeln!(f, "max |A - B|")?;
+            for m in &self.max_dev {
+                writeln!(
+                    f,
+                    "  {:<18} {:.3e} at t={} ({} vs {})",
+                    m.field, m.abs_diff, m.t, m.a, m.b
+                )?;
+            }
+        }
+        if let Some(rate) = &self.ignition_rate {
+            writeln!(
+                f,
+                "{:<20} {:>10} {:>10} {:>10}",
+                "aggregate", "A", "B", "shift"
+            )?;
+            let mut line = |name: &str, s: &Shift| {
+                writeln!(
+                    f,
+                    "  {:<18} {:>10.4} {:>10.4} {:>+10.4}",
+                    name, s.a, s.b, s.shift
+                )
+            };
+            line("ignition rate", rate)?;
+            if let Some(s) = &self.mean_d_g_local {
+                line("mean d_g_local", s)?;
+            }
+            if let Some(s) = &self.mean_d_g_broadcast {
+                line("mean d_g_broadcast", s)?;
+            }
+            for r in IGNITE_REASONS {
+                if let Some(s) = self.ignite_reasons.get(r) {
+                    line(r, s)?;
+                }
+            }
+            for (r, s) in &self.ignite_reasons {
+                if !IGNITE_REASONS.contains(&r.as_str()) {
+                    line(r, s)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::io::{BufRead, Lines};
+use std::marker::PhantomData;
+
+use crate::util::{ravel_multi_index, IndexError};
+
+// One observation in a sniff stream (data/sniff_stream.ndjson schema).
+#[derive(Debug, Clone, Deserialize)]
+#[allow(non_snake_case)]
+pub struct StreamEvent {
+    pub t: u64,
+    pub o: Vec<usize>,
+    // Both may be omitted when the kernel has a full A tensor loaded.
+    #[serde(default)]
+    pub A_shape: Vec<usize>,
+    #[serde(alias = "A_flat_col", default)]
+    pub a_flat_col: Vec<f64>,
+    pub tactile_flat_col: Option<Vec<f64>>,
+    pub p_prior: Vec<f64>,
+    pub task_vec: Vec<f64>,
+    pub q0: Option<Vec<f64>>,
+    pub sniff_strength: Option<f64>,
+    pub touch_pressure: Option<f64>,
+    // Discrete action selecting the transition matrix (transition.index = action_id).
+    #[serde(default)]
+    pub action_id: Option<usize>,
+}
+
+impl StreamEvent {
+    // `o` must index into the observation dims of `A_shape` (when given).
+    pub fn check_observation(&self) -> std::result::Result<(), IndexError> {
+        if let Some(o_shape) = self.A_shape.get(1..) {
+            ravel_multi_index(&self.o, o_shape)?;
+        }
+        Ok(())
+    }
+}
+
+// Line-numbered NDJSON event reader. Blank lines are skipped; every error
+// names the 1-based line it came from. `T` is the on-disk schema, converted
+// into a StreamEvent before the observation is checked.
+pub struct EventReader<R, T = StreamEvent> {
+    lines: Lines<R>,
+    line_no: usize,
+    _schema: PhantomData<T>,
+}
+
+impl<R: BufRead, T> EventReader<R, T> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_no: 0,
+            _schema: PhantomData,
+        }
+    }
+}
+
+impl<R, T> Iterator for EventReader<R, T>
+where
+    R: BufRead,
+    T: DeserializeOwned + Into<StreamEvent>,
+{
+    type Item = Result<(usize, StreamEvent)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            self.line_no += 1;
+            let line_no = self.line_no;
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => {
+                    return Some(Err(
+                        anyhow::Error::from(e).context(format!("line {}", line_no))
+                    ))
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = serde_json::from_str::<T>(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(|raw| {
+                    let ev: StreamEvent = raw.into();
+                    ev.check_observation()?;
+                    Ok(ev)
+                })
+                .with_context(|| format!("line {}", line_no));
+            return Some(parsed.map(|ev| (line_no, ev)));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::flatten::{column_name, walk, Leaf, Seg};
+
+// Flat tables built from ledger rows, written as CSV or as a small binary
+// columnar file (see `write_columnar` for the layout).
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    // One line per row; vectors spread over `name_0..n` columns.
+    #[default]
+    Wide,
+    // One line per numeric value: t, field, index, value.
+    Long,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wide" => Ok(Layout::Wide),
+            "long" => Ok(Layout::Long),
+            other => Err(format!(
+                "unknown layout {:?} (expected wide or long)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Columnar,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "columnar" | "col" => Ok(Format::Columnar),
+            other => Err(format!(
+                "unknown format {:?} (expected csv or columnar)",
+                other
+            )),
+        }
+    }
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Columnar => "lncol",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Cell {
+    fn from_leaf(leaf: Leaf) -> Self {
+        match leaf {
+            Leaf::Null => Cell::Null,
+            Leaf::Bool(b) => Cell::Bool(b),
+            Leaf::Int(i) => Cell::Int(i),
+            Leaf::UInt(u) => i64::try_from(u)
+                .map(Cell::Int)
+                .unwrap_or(Cell::Float(u as f64)),
+            Leaf::Float(x) => Cell::Float(x),
+            Leaf::Str(s) => Cell::Str(s.to_string()),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Cell::Bool(b) => Some(*b as u8 as f64),
+            Cell::Int(i) => Some(*i as f64),
+            Cell::Float(x) => Some(*x),
+            Cell::Null | Cell::Str(_) => None,
+        }
+    }
+
+    fn csv(&self) -> String {
+        match self {
+            Cell::Null => String::new(),
+            Cell::Bool(b) => b.to_string(),
+            Cell::Int(i) => i.to_string(),
+            Cell::Float(x) => format!("{:?}", x),
+            Cell::Str(s) if s.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            }
+            Cell::Str(s) => s.clone(),
+        }
+    }
+}
+
+// One flattened value: top-level field path, element index (if inside a
+// vector) and the scalar.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatValue {
+    pub field: String,
+    pub index: Option<String>,
+    pub cell: Cell,
+}
+
+impl FlatValue {
+    pub fn column(&self) -> String {
+        match &self.index {
+            Some(i) => format!("{}_{}", self.field, i),
+            None => self.field.clone(),
+        }
+    }
+}
+
+pub fn flatten_row<T: Serialize>(row: &T) -> Result<Vec<FlatValue>> {
+    let mut out = Vec::new();
+    walk(row, |path, leaf| {
+        let split = path
+            .iter()
+            .position(|s| matches!(s, Seg::Index(_)))
+            .unwrap_or(path.len());
+        let (field, index) = path.split_at(split);
+        out.push(FlatValue {
+            field: column_name(field),
+            index: (!index.is_empty()).then(|| column_name(index)),
+            cell: Cell::from_leaf(leaf),
+        });
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)?;
+    Ok(out)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DType {
+    F64,
+    I64,
+    Bool,
+    Utf8,
+}
+
+impl DType {
+    fn code(self) -> u8 {
+        match self {
+            DType::F64 => 0,
+            DType::I64 => 1,
+            DType::Bool => 2,
+            DType::Utf8 => 3,
+        }
+    }
+
+    fn from_code(c: u8) -> Result<Self> {
+        Ok(match c {
+            0 => DType::F64,
+            1 => DType::I64,
+            2 => DType::Bool,
+            3 => DType::Utf8,
+            other => anyhow::bail!("unknown column dtype code {}", other),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub cells: Vec<Cell>,
+}
+
+impl Column {
+    // Narrowest type holding every non-null cell: ints widen to f64, and any
+    // string (or a bool mixed with numbers) makes the column text.
+    pub fn dtype(&self) -> DType {
+        let (mut b, mut i, mut f, mut s) = (false, false, false, false);
+        for c in &self.cells {
+            match c {
+                Cell::Null => {}
+                Cell::Bool(_) => b = true,
+                Cell::Int(_) => i = true,
+                Cell::Float(_) => f = true,
+                Cell::Str(_) => s = true,
+            }
+        }
+        match (b, i, f, s) {
+            (_, _, _, true) => DType::Utf8,
+            (true, false, false, false) => DType::Bool,
+            (true, _, _, _) => DType::Utf8,
+            (false, true, false, false) => DType::I64,
+            _ => DType::F64,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    pub columns: Vec<Column>,
+}
+
+impl Table {
+    pub fn n_rows(&self) -> usize {
+        self.columns.first().map(|c| c.cells.len()).unwrap_or(0)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    // Columns in first-seen order; rows lacking a column get Null.
+    pub fn wide<T: Serialize>(rows: &[T]) -> Result<Self> {
+        let mut table = Table::default();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            for v in flatten_row(row)? {
+                let name = v.column();
+                let c = *index.entry(name.clone()).or_insert_with(|| {
+                    table.columns.push(Column {
+                        name,
+                        cells: vec![Cell::Null; r],
+                    });
+                    table.columns.len() - 1
+                });
+                table.columns[c].cells.push(v.cell);
+            }
+            for col in table.columns.iter_mut() {
+                col.cells.resize(r + 1, Cell::Null);
+            }
+        }
+        Ok(table)
+    }
+
+    // Numeric and boolean values only (bools as 0/1); text fields such as
+    // `ignite_reason` are only in the wide layout.
+    pub fn long<T: Serialize>(rows: &[T]) -> Result<Self> {
+        let names = ["t", "field", "index", "value"];
+        let mut cols: Vec<Vec<Cell>> = vec![Vec::new(); names.len()];
+        for row in rows {
+            let flat = flatten_row(row)?;
+            let t = flat
+                .iter()
+                .find(|v| v.field == "t" && v.index.is_none())
+                .map(|v| v.cell.clone())
+                .unwrap_or(Cell::Null);
+            for v in flat
+                .iter()
+                .filter(|v| !(v.field == "t" && v.index.is_none()))
+            {
+                let Some(x) = v.cell.as_f64() else { continue };
+                cols[0].push(t.clone());
+                cols[1].push(Cell::Str(v.field.clone()));
+                cols[2].push(
+                    v.index
+                        .as_ref()
+                        .and_then(|i| i.parse::<i64>().ok())
+                        .map(Cell::Int)
+                        .unwrap_or(Cell::Null),
+                );
+                cols[3].push(Cell::Float(x));
+            }
+        }
+        Ok(Table {
+            columns: names
+                .iter()
+                .zip(cols)
+                .map(|(n, cells)| Column {
+                    name: n.to_string(),
+                    cells,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn build<T: Serialize>(rows: &[T], layout: Layout) -> Result<Self> {
+        match layout {
+            Layout::Wide => Self::wide(rows),
+            Layout::Long => Self::long(rows),
+        }
+    }
+}
+
+pub fn write_csv<W: Write>(w: &mut W, table: &Table) -> Result<()> {
+    let header: Vec<String> = table
+        .columns
+        .iter()
+        .map(|c| Cell::Str(c.name.clone()).csv())
+        .collect();
+    writeln!(w, "{}", header.join(","))?;
+    for r in 0..table.n_rows() {
+        let line: Vec<String> = table.columns.iter().map(|c| c.cells[r].csv()).collect();
+        writeln!(w, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+pub const COLUMNAR_MAGIC: &[u8; 8] = b"LNECOL01";
+
+// Columnar layout, all integers little-endian:
+//   magic "LNECOL01", u32 n_columns, u64 n_rows, then per column:
+//   u16 name length, name (UTF-8), u8 dtype (0 f64, 1 i64, 2 bool, 3 utf8),
+//   n_rows validity bytes (1 = present), then the values: n_rows x f64 / i64
+//   / u8, or for utf8 a u32 byte length plus bytes per row. Nulls are stored
+//   as 0 / empty and flagged in the validity bytes.
+pub fn write_columnar<W: Write>(w: &mut W, table: &Table) -> Result<()> {
+    let n = table.n_rows();
+    w.write_all(COLUMNAR_MAGIC)?;
+    w.write_all(&(table.columns.len() as u32).to_le_bytes())?;
+    w.write_all(&(n as u64).to_le_bytes())?;
+    for col in &table.columns {
+        let name = col.name.as_bytes();
+        let name_len = u16::try_from(name.len())
+            .with_context(|| format!("column name too long: {}", col.name))?;
+        w.write_all(&name_len.to_le_bytes())?;
+        w.write_all(name)?;
+        let dtype = col.dtype();
+        w.write_all(&[dtype.code()])?;
+        let validity: Vec<u8> = col.cells.iter().map(|c| (*c != Cell::Null) as u8).collect();
+        w.write_all(&validity)?;
+        for c in &col.cells {
+            match dtype {
+                DType::F64 => w.write_all(&c.as_f64().unwrap_or(0.0).to_le_bytes())?,
+                DType::I64 => {
+                    let v = if let Cell::Int(i) = c { *i } else { 0 };
+                    w.write_all(&v.to_le_bytes())?
+                }
+                DType::Bool => w.write_all(&[matches!(c, Cell::Bool(true)) as u8])?,
+                DType::Utf8 => {
+                    let s = match c {
+                        Cell::Null => String::new(),
+                        Cell::Str(s) => s.clone(),
+                        other => other.csv(),
+                    };
+                    w.write_all(&(s.len() as u32).to_le_bytes())?;
+                    w.write_all(s.as_bytes())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_array<const N: usize, R: Read>(r: &mut R) -> Result<[u8; N]> {
+    let mut b = [0u8; N];
+    r.read_exact(&mut b).context("truncated columnar file")?;
+    Ok(b)
+}
+
+pub fn read_columnar<R: Read>(r: &mut R) -> Result<Table> {
+    if &read_array::<8, _>(r)? != COLUMNAR_MAGIC {
+        anyhow::bail!("not a columnar export (bad magic)");
+    }
+    let n_cols = u32::from_le_bytes(read_array(r)?) as usize;
+    let n = u64::from_le_bytes(read_array(r)?) as usize;
+    let mut table = Table::default();
+    for _ in 0..n_cols {
+        let name_len = u16::from_le_bytes(read_array(r)?) as usize;
+        let mut name = vec![0u8; name_len];
+        r.read_exact(&mut name).context("truncated columnar file")?;
+        let name = String::from_utf8(name)?;
+        let dtype = DType::from_code(read_array::<1, _>(r)?[0])?;
+        let mut validity = vec![0u8; n];
+        r.read_exact(&mut validity)
+            .context("truncated columnar file")?;
+        let mut cells = Vec::with_capacity(n);
+        for &valid in &validity {
+            let cell = match dtype {
+                DType::F64 => Cell::Float(f64::from_le_bytes(read_array(r)?)),
+                DType::I64 => Cell::Int(i64::from_le_bytes(read_array(r)?)),
+                DType::Bool => Cell::Bool(read_array::<1, _>(r)?[0] != 0),
+                DType::Utf8 => {
+                    let len = u32::from_le_bytes(read_array(r)?) as usize;
+                    let mut s = vec![0u8; len];
+                    r.read_exact(&mut s).context("truncated columnar file")?;
+                    Cell::Str(String::from_utf8(s)?)
+                }
+            };
+            cells.push(if valid == 0 { Cell::Null } else { cell });
+        }
+        table.columns.push(Column { name, cells });
+    }
+    Ok(table)
+}
@@ -0,0 +1,27 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::flatten::{dotted, walk, Leaf};
+
+// serde_json writes NaN and ±inf as `null`, which reads back as a missing
+// value. `check_finite` reports the first non-finite float with its field
+// path, e.g. `q_after[2] = NaN`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonFinite(pub String);
+
+impl fmt::Display for NonFinite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "non-finite float {}", self.0)
+    }
+}
+
+impl std::error::Error for NonFinite {}
+
+pub fn check_finite<T: Serialize + ?Sized>(value: &T) -> Result<(), NonFinite> {
+    walk(value, |path, leaf| match leaf {
+        Leaf::Float(v) if !v.is_finite() => Err(format!("{} = {}", dotted(path), v)),
+        _ => Ok(()),
+    })
+    .map_err(NonFinite)
+}
@@ -0,0 +1,371 @@
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+// Walks a value through serde and hands every scalar leaf, with its path, to
+// a callback. Used to check rows before writing (`finite`) and to flatten
+// ledger rows into columns (`export`).
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Seg {
+    Field(&'static str),
+    Index(usize),
+    Key(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Leaf<'a> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(&'a str),
+}
+
+// `q_after[2]`, `params.gamma`
+pub fn dotted(path: &[Seg]) -> String {
+    let mut s = String::new();
+    for seg in path {
+        match seg {
+            Seg::Index(i) => s.push_str(&format!("[{}]", i)),
+            Seg::Field(f) => {
+                if !s.is_empty() {
+                    s.push('.');
+                }
+                s.push_str(f);
+            }
+            Seg::Key(k) => {
+                if !s.is_empty() {
+                    s.push('.');
+                }
+                s.push_str(k);
+            }
+        }
+    }
+    if s.is_empty() {
+        s.push_str("value");
+    }
+    s
+}
+
+// `q_after_2`, `params_gamma`
+pub fn column_name(path: &[Seg]) -> String {
+    path.iter()
+        .map(|seg| match seg {
+            Seg::Field(f) => f.to_string(),
+            Seg::Index(i) => i.to_string(),
+            Seg::Key(k) => k.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[derive(Debug)]
+pub struct WalkError(pub String);
+
+impl fmt::Display for WalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for WalkError {}
+
+impl ser::Error for WalkError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        WalkError(msg.to_string())
+    }
+}
+
+// Stops at the first Err returned by `f`.
+pub fn walk<T, F>(value: &T, mut f: F) -> Result<(), String>
+where
+    T: Serialize + ?Sized,
+    F: FnMut(&[Seg], Leaf) -> Result<(), String>,
+{
+    let mut w = Walker {
+        path: Vec::new(),
+        f: &mut f,
+    };
+    value.serialize(&mut w).map_err(|e| e.0)
+}
+
+struct Walker<'f, F> {
+    path: Vec<Seg>,
+    f: &'f mut F,
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> Walker<'_, F> {
+    fn leaf(&mut self, leaf: Leaf) -> Result<(), WalkError> {
+        (self.f)(&self.path, leaf).map_err(WalkError)
+    }
+
+    fn nested<T: Serialize + ?Sized>(&mut self, seg: Seg, v: &T) -> Result<(), WalkError> {
+        self.path.push(seg);
+        let r = v.serialize(&mut *self);
+        self.path.pop();
+        r
+    }
+}
+
+struct Compound<'a, 'f, F> {
+    w: &'a mut Walker<'f, F>,
+    idx: usize,
+    key: Option<String>,
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> Compound<'_, '_, F> {
+    fn element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        let seg = Seg::Index(self.idx);
+        self.idx += 1;
+        self.w.nested(seg, v)
+    }
+}
+
+impl<'a, 'f, F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::Serializer
+    for &'a mut Walker<'f, F>
+{
+    type Ok = ();
+    type Error = WalkError;
+    type SerializeSeq = Compound<'a, 'f, F>;
+    type SerializeTuple = Compound<'a, 'f, F>;
+    type SerializeTupleStruct = Compound<'a, 'f, F>;
+    type SerializeTupleVariant = Compound<'a, 'f, F>;
+    type SerializeMap = Compound<'a, 'f, F>;
+    type SerializeStruct = Compound<'a, 'f, F>;
+    type SerializeStructVariant = Compound<'a, 'f, F>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), WalkError> {
+        self.leaf(Leaf::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), WalkError> {
+        self.leaf(Leaf::Int(v as i64))
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), WalkError> {
+        self.leaf(Leaf::Int(v as i64))
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), WalkError> {
+        self.leaf(Leaf::Int(v as i64))
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), WalkError> {
+        self.leaf(Leaf::Int(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), WalkError> {
+        self.leaf(Leaf::UInt(v as u64))
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), WalkError> {
+        self.leaf(Leaf::UInt(v as u64))
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), WalkError> {
+        self.leaf(Leaf::UInt(v as u64))
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), WalkError> {
+        self.leaf(Leaf::UInt(v))
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), WalkError> {
+        self.leaf(Leaf::Float(v as f64))
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), WalkError> {
+        self.leaf(Leaf::Float(v))
+    }
+    fn serialize_char(self, v: char) -> Result<(), WalkError> {
+        self.leaf(Leaf::Str(v.encode_utf8(&mut [0u8; 4])))
+    }
+    fn serialize_str(self, v: &str) -> Result<(), WalkError> {
+        self.leaf(Leaf::Str(v))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), WalkError> {
+        for (i, b) in v.iter().enumerate() {
+            self.path.push(Seg::Index(i));
+            let r = self.leaf(Leaf::UInt(*b as u64));
+            self.path.pop();
+            r?;
+        }
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), WalkError> {
+        self.leaf(Leaf::Null)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<(), WalkError> {
+        v.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), WalkError> {
+        self.leaf(Leaf::Null)
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), WalkError> {
+        self.leaf(Leaf::Null)
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), WalkError> {
+        self.leaf(Leaf::Str(variant))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        v: &T,
+    ) -> Result<(), WalkError> {
+        v.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<(), WalkError> {
+        self.nested(Seg::Field(variant), v)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, WalkError> {
+        Ok(Compound {
+            w: self,
+            idx: 0,
+            key: None,
+        })
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, WalkError> {
+        self.serialize_seq(None)
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, WalkError> {
+        self.serialize_seq(None)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, WalkError> {
+        self.serialize_seq(None)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, WalkError> {
+        self.serialize_seq(None)
+    }
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, WalkError> {
+        self.serialize_seq(None)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, WalkError> {
+        self.serialize_seq(None)
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeSeq for Compound<'_, '_, F> {
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeTuple for Compound<'_, '_, F> {
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeTupleStruct
+    for Compound<'_, '_, F>
+{
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeTupleVariant
+    for Compound<'_, '_, F>
+{
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        self.element(v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+// Map keys become path segments via their JSON form (strings unquoted).
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeMap for Compound<'_, '_, F> {
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, k: &T) -> Result<(), WalkError> {
+        let key = match serde_json::to_value(k) {
+            Ok(serde_json::Value::String(s)) => s,
+            Ok(v) => v.to_string(),
+            Err(e) => return Err(WalkError(e.to_string())),
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), WalkError> {
+        match self.key.take() {
+            Some(k) => self.w.nested(Seg::Key(k), v),
+            None => self.element(v),
+        }
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeStruct for Compound<'_, '_, F> {
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), WalkError> {
+        self.w.nested(Seg::Field(key), v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(&[Seg], Leaf) -> Result<(), String>> ser::SerializeStructVariant
+    for Compound<'_, '_, F>
+{
+    type Ok = ();
+    type Error = WalkError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), WalkError> {
+        self.w.nested(Seg::Field(key), v)
+    }
+    fn end(self) -> Result<(), WalkError> {
+        Ok(())
+    }
+}
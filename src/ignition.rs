@@ -2,6 +2,7 @@ use ndarray::Array2;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Params {
     pub alpha: f64,
     pub beta: f64,
@@ -10,6 +11,18 @@ pub struct Params {
     pub delta: f64,
 }
 
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            alpha: 0.10,
+            beta: 0.25,
+            gamma: 0.80,
+            c_crit: 0.70,
+            delta: 0.05,
+        }
+    }
+}
+
 pub fn efficiency(e: f64, p: f64, k: f64) -> f64 {
     (e + p) / (k + 1e-9)
 }
@@ -0,0 +1,817 @@
+use anyhow::Result;
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::adapter::{bayes_update, normalize};
+use crate::broadcast::{apply_broadcast, expand_rg_to_n, rg_avg_pool};
+use crate::event::StreamEvent;
+use crate::ignition::{coherence, efficiency, Params};
+use crate::ledger::{ReplayRow, TraceRow};
+use crate::likelihood::LikelihoodTensor;
+use crate::memory::{MemoryFeatures, MemoryMode, MemoryRow, MemoryState, DEFAULT_LAST_K};
+use crate::policy::{
+    EfeConfig, FixedConfig, Forecast, Policy, PolicyInput, PolicyOutput, PolicyRegistry,
+    RandomConfig, SmoothConfig,
+};
+use crate::sensory::{sensory_multimodal, ActionParams};
+use crate::transition::TransitionConfig;
+use crate::util::{ravel_multi_index, safe_ln_n};
+
+const EPS: f64 = 1e-9;
+
+// Which prior the Bayesian update starts from at each step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    // Every step restarts from the event's own p_prior.
+    #[default]
+    StaticPrior,
+    // The previous q_next is the prior, mixed with p_prior by `forget`.
+    Sequential,
+}
+
+// Knobs of one ignition cycle. Defaults are the values the binaries shipped with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KernelConfig {
+    pub params: Params,
+    pub rg_level: usize,
+    pub rg_cost: f64,
+    pub lambda_broadcast: f64,
+    pub mem_window: usize,
+    // Rows behind the last-k memory features (`MemoryFeatures::recent`).
+    pub mem_last_k: usize,
+    // Sliding window of `mem_window` rows, or exponential decay with
+    // `mem_half_life` (in rows; `mem_window` is then unused).
+    pub mem_mode: MemoryMode,
+    pub mem_half_life: f64,
+    // Olfactory weight when fusing with tactile data; None derives it from the action.
+    pub w_olf: Option<f64>,
+    pub filter: FilterMode,
+    // Sequential mode only: prior = (1 - forget) * q_prev + forget * p_prior.
+    pub forget: f64,
+    // Optional B matrices propagating the carried belief before each update.
+    pub transition: Option<TransitionConfig>,
+    // Optional full A tensor file (.npy or JSON); events then only need `o`.
+    pub a_tensor: Option<PathBuf>,
+    // Action rule for events without sniff_strength/touch_pressure, by name
+    // from `PolicyRegistry::builtin`, with each policy's settings.
+    pub policy: String,
+    pub efe: EfeConfig,
+    pub fixed: FixedConfig,
+    pub random: RandomConfig,
+    pub smooth: SmoothConfig,
+}
+
+impl Default for KernelConfig {
+    fn default() -> Self {
+        Self {
+            params: Params::default(),
+            rg_level: 1,
+            rg_cost: 0.1,
+            lambda_broadcast: 1.0,
+            mem_window: 64,
+            mem_last_k: DEFAULT_LAST_K,
+            mem_mode: MemoryMode::Window,
+            mem_half_life: 16.0,
+            w_olf: None,
+            filter: FilterMode::StaticPrior,
+            forget: 0.0,
+            transition: None,
+            a_tensor: None,
+            policy: "heuristic".to_string(),
+            efe: EfeConfig::default(),
+            fixed: FixedConfig::default(),
+            random: RandomConfig::default(),
+            smooth: SmoothConfig::default(),
+        }
+    }
+}
+
+impl KernelConfig {
+    // Range checks that do not depend on the data.
+    pub fn validate(&self) -> Result<()> {
+        let p = &self.params;
+        for (name, v) in [
+            ("alpha", p.alpha),
+            ("beta", p.beta),
+            ("gamma", p.gamma),
+            ("c_crit", p.c_crit),
+            ("delta", p.delta),
+            ("rg_cost", self.rg_cost),
+            ("lambda_broadcast", self.lambda_broadcast),
+            ("forget", self.forget),
+        ] {
+            if !v.is_finite() {
+                anyhow::bail!("{} must be finite, got {}", name, v);
+            }
+        }
+        if p.alpha < 0.0 || p.beta < 0.0 {
+            anyhow::bail!(
+                "alpha and beta must be >= 0, got {} and {}",
+                p.alpha,
+                p.beta
+            );
+        }
+        if !(p.gamma > 0.0 && p.gamma <= 1.0) {
+            anyhow::bail!("gamma must be in (0,1], got {}", p.gamma);
+        }
+        if !(0.0..=1.0).contains(&p.c_crit) {
+            anyhow::bail!("c_crit must be in [0,1], got {}", p.c_crit);
+        }
+        if self.rg_cost < 0.0 {
+            anyhow::bail!("rg_cost must be >= 0, got {}", self.rg_cost);
+        }
+        if self.lambda_broadcast < 0.0 {
+            anyhow::bail!(
+                "lambda_broadcast must be >= 0, got {}",
+                self.lambda_broadcast
+            );
+        }
+        if self.rg_level >= usize::BITS as usize {
+            anyhow::bail!("rg_level {} is out of range", self.rg_level);
+        }
+        if self.mem_window == 0 {
+            anyhow::bail!("mem_window must be >= 1");
+        }
+        if self.mem_last_k == 0 {
+            anyhow::bail!("mem_last_k must be >= 1");
+        }
+        if !(self.mem_half_life.is_finite() && self.mem_half_life > 0.0) {
+            anyhow::bail!("mem_half_life must be > 0, got {}", self.mem_half_life);
+        }
+        if !(0.0..=1.0).contains(&self.forget) {
+            anyhow::bail!("forget must be in [0,1], got {}", self.forget);
+        }
+        if let Some(w) = self.w_olf {
+            if !(0.0..=1.0).contains(&w) {
+                anyhow::bail!("w_olf must be in [0,1], got {}", w);
+            }
+        }
+        if let Some(tr) = &self.transition {
+            tr.validate()?;
+            // A static prior restarts every update from p_prior, so a predicted
+            // q_before would never reach the posterior.
+            if self.filter != FilterMode::Sequential {
+                anyhow::bail!("transition needs filter = \"sequential\"");
+            }
+        }
+        let registry = PolicyRegistry::builtin();
+        if !registry.contains(&self.policy) {
+            anyhow::bail!(
+                "unknown policy {:?} (known: {})",
+                self.policy,
+                registry.names().join(", ")
+            );
+        }
+        self.efe.validate()?;
+        self.smooth.validate()?;
+        if self.fixed.actions.is_empty() {
+            anyhow::bail!("fixed.actions is empty");
+        }
+        for (name, [lo, hi]) in [
+            ("random.sniff_range", self.random.sniff_range),
+            ("random.touch_range", self.random.touch_range),
+        ] {
+            if !(lo.is_finite() && hi.is_finite() && 0.0 <= lo && lo <= hi) {
+                anyhow::bail!("{} must satisfy 0 <= lo <= hi, got [{}, {}]", name, lo, hi);
+            }
+        }
+        Ok(())
+    }
+
+    // RG pooling must leave at least one block of the belief vector, and
+    // transition matrices must match the belief size.
+    pub fn validate_for_belief(&self, n: usize) -> Result<()> {
+        if self.rg_level >= usize::BITS as usize || (1usize << self.rg_level) > n {
+            anyhow::bail!(
+                "rg_level {} pools blocks of 2^{} but belief size is {}",
+                self.rg_level,
+                self.rg_level,
+                n
+            );
+        }
+        if let Some(tr) = &self.transition {
+            if tr.n_states() != n {
+                anyhow::bail!(
+                    "transition matrices are {}x{} but belief size is {}",
+                    tr.n_states(),
+                    tr.n_states(),
+                    n
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Msg {
+    pub level: u8,
+    pub dx: Array1<f64>,
+    pub prec: Array1<f64>,
+    pub e: f64,
+    pub p: f64,
+    pub k: f64,
+}
+
+pub fn entropy(q: &Array1<f64>) -> f64 {
+    let q = q.mapv(|x| x.max(EPS));
+    let q = &q / (q.sum().max(EPS));
+    -q.iter().map(|&x| x * x.ln()).sum::<f64>()
+}
+
+pub fn kl(q: &Array1<f64>, p: &Array1<f64>) -> f64 {
+    let q = q.mapv(|x| x.max(EPS));
+    let p = p.mapv(|x| x.max(EPS));
+    let q = &q / (q.sum().max(EPS));
+    let p = &p / (p.sum().max(EPS));
+    q.iter()
+        .zip(p.iter())
+        .map(|(&qi, &pi)| qi * (qi.ln() - pi.ln()))
+        .sum()
+}
+
+// G = KL(q||p) - Eq log p(o|s)
+pub fn vfe(q: &Array1<f64>, p_prior: &Array1<f64>, likelihood_col: &Array1<f64>) -> f64 {
+    let qn = normalize(q);
+    let pn = normalize(p_prior);
+    let lik = likelihood_col.mapv(|x| x.max(EPS));
+    let eloglik = qn
+        .iter()
+        .zip(lik.iter())
+        .map(|(&qi, &li)| qi * li.ln())
+        .sum::<f64>();
+    kl(&qn, &pn) - eloglik
+}
+
+pub fn precision_from_dx(dx: &Array1<f64>, task: &Array1<f64>, level: u8) -> Array1<f64> {
+    let mut prec = dx.mapv(|x| x.abs());
+    if level == 1 {
+        let l = prec.len().min(task.len());
+        for i in 0..l {
+            prec[i] += 0.1 * task[i];
+        }
+    }
+    let norm = prec.iter().map(|x| x * x).sum::<f64>().sqrt().max(EPS);
+    prec.mapv(|x| x / norm)
+}
+
+pub fn msg_metrics(
+    q_before: &Array1<f64>,
+    q_after: &Array1<f64>,
+    task: &Array1<f64>,
+    level: u8,
+) -> Msg {
+    let qb = normalize(q_before);
+    let qa = normalize(q_after);
+    let dx = &qa - &qb;
+
+    let e = kl(&qa, &qb);
+    let p = entropy(&qb) - entropy(&qa);
+    let l2 = dx.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let nnz = dx.iter().filter(|&&x| x.abs() > 1e-6).count() as f64;
+    let k = l2 + 0.5 * (nnz / (dx.len() as f64 + EPS));
+
+    let prec = precision_from_dx(&dx, task, level);
+    Msg {
+        level,
+        dx,
+        prec,
+        e,
+        p,
+        k,
+    }
+}
+
+pub fn task_biased_belief(q: &Array1<f64>, task: &Array1<f64>, gain: f64) -> Array1<f64> {
+    let mut logits = q.mapv(|x| x.max(EPS).ln());
+    let l = logits.len().min(task.len());
+    for i in 0..l {
+        logits[i] += gain * task[i];
+    }
+    let m = logits.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let ex = logits.mapv(|x| (x - m).exp());
+    &ex / (ex.sum().max(EPS))
+}
+
+// Everything one ignition cycle derives from (q_before, p_prior, likelihood, task).
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub u_t: f64,
+    pub theta: f64,
+    pub g_before: f64,
+    pub g_after_local: f64,
+    pub g_after_broadcast: f64,
+    pub d_g_local: f64,
+    pub d_g_broadcast: f64,
+    pub q_after: Array1<f64>,
+    pub q_broadcast: Array1<f64>,
+    pub q_next: Array1<f64>,
+    pub survivors: Vec<Msg>,
+    pub coherence: f64,
+    pub broadcast: Array1<f64>,
+    pub b_expanded: Array1<f64>,
+    pub ignited: bool,
+    pub ignite_reason: String,
+}
+
+impl Evaluation {
+    pub fn survivor_levels(&self) -> Vec<u8> {
+        self.survivors.iter().map(|m| m.level).collect()
+    }
+
+    // (dx, e, p, k, eta) of the first survivor, zeros when none survived.
+    pub fn lead_msg(&self) -> (Array1<f64>, f64, f64, f64, f64) {
+        match self.survivors.first() {
+            None => (Array1::from_vec(vec![]), 0.0, 0.0, 0.0, 0.0),
+            Some(m) => (m.dx.clone(), m.e, m.p, m.k, efficiency(m.e, m.p, m.k)),
+        }
+    }
+}
+
+// Prior for the update: the event's `p_prior` (static) or, sequentially, the
+// predicted belief mixed with it at rate `forget`.
+fn step_prior(cfg: &KernelConfig, q_before: &Array1<f64>, p_prior: &Array1<f64>) -> Array1<f64> {
+    match cfg.filter {
+        FilterMode::StaticPrior => p_prior.clone(),
+        FilterMode::Sequential => {
+            let f = cfg.forget;
+            let mixed = q_before.mapv(|x| (1.0 - f) * x) + &p_prior.mapv(|x| f * x);
+            normalize(&mixed)
+        }
+    }
+}
+
+// Every `ignite_reason`, in the order `evaluate` tests them.
+pub const IGNITE_REASONS: [&str; 4] = ["no_survivors", "coherence_fail", "deltaG_fail", "ignite"];
+
+// Pure ignition cycle: local update, survivor selection, coherence, broadcast, ignition test.
+pub fn evaluate(
+    cfg: &KernelConfig,
+    q_before: &Array1<f64>,
+    p_prior: &Array1<f64>,
+    lik_col: &Array1<f64>,
+    task: &Array1<f64>,
+) -> Evaluation {
+    let params = &cfg.params;
+    let n = p_prior.len();
+
+    let u_t = entropy(&normalize(q_before)) / safe_ln_n(n);
+    let g_before = vfe(q_before, p_prior, lik_col);
+
+    let q_after = bayes_update(&normalize(p_prior), &normalize(lik_col));
+    let g_after_local = vfe(&q_after, p_prior, lik_col);
+
+    let q_task = task_biased_belief(&q_after, task, 0.05);
+
+    let m0 = msg_metrics(q_before, &q_after, task, 0);
+    let m1 = msg_metrics(&q_after, &q_task, task, 1);
+
+    let theta = params.alpha + params.beta * u_t;
+
+    let mut survivors: Vec<Msg> = Vec::new();
+    for m in [m0, m1] {
+        let eta = efficiency(m.e, m.p, m.k);
+        if eta < theta {
+            continue;
+        }
+        let dx_rg = rg_avg_pool(&m.dx, cfg.rg_level);
+        let k_rg = m.k + cfg.rg_cost;
+        let eta_rg = efficiency(m.e, m.p, k_rg);
+        if eta_rg >= params.gamma * theta {
+            survivors.push(Msg { dx: dx_rg, ..m });
+        }
+    }
+
+    let survivors_n = survivors.len();
+
+    let coh = if survivors_n == 0 {
+        0.0
+    } else {
+        let d = survivors[0].prec.len();
+        let mut mat: Vec<f64> = Vec::with_capacity(survivors_n * d);
+        for m in &survivors {
+            mat.extend_from_slice(m.prec.as_slice().unwrap());
+        }
+        let precisions = Array2::from_shape_vec((survivors_n, d), mat).unwrap();
+        coherence(&precisions)
+    };
+
+    let broadcast = if survivors_n == 0 {
+        Array1::from_vec(vec![])
+    } else {
+        let mut etas: Vec<f64> = survivors
+            .iter()
+            .map(|m| efficiency(m.e, m.p, m.k))
+            .collect();
+        let max_eta = etas.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        for x in etas.iter_mut() {
+            *x = (*x - max_eta).exp();
+        }
+        let z = etas.iter().sum::<f64>().max(EPS);
+
+        let mut b = Array1::from_vec(vec![0.0; survivors[0].dx.len()]);
+        for (w_raw, m) in etas.iter().zip(survivors.iter()) {
+            let w = *w_raw / z;
+            b += &(m.dx.mapv(|x| x * w));
+        }
+        b
+    };
+
+    let b_expanded = expand_rg_to_n(&broadcast, n, cfg.rg_level);
+    let q_broadcast = apply_broadcast(&q_after, &b_expanded, cfg.lambda_broadcast);
+    let g_after_broadcast = vfe(&q_broadcast, p_prior, lik_col);
+
+    let d_g_local = g_before - g_after_local;
+    let d_g_broadcast = g_before - g_after_broadcast;
+
+    let ignite_reason = if survivors_n == 0 {
+        "no_survivors"
+    } else if coh < params.c_crit {
+        "coherence_fail"
+    } else if d_g_broadcast < params.delta {
+        "deltaG_fail"
+    } else {
+        "ignite"
+    }
+    .to_string();
+
+    let ignited = ignite_reason == "ignite";
+
+    let q_next = if ignited {
+        q_broadcast.clone()
+    } else {
+        q_after.clone()
+    };
+
+    Evaluation {
+        u_t,
+        theta,
+        g_before,
+        g_after_local,
+        g_after_broadcast,
+        d_g_local,
+        d_g_broadcast,
+        q_after,
+        q_broadcast,
+        q_next,
+        survivors,
+        coherence: coh,
+        broadcast,
+        b_expanded,
+        ignited,
+        ignite_reason,
+    }
+}
+
+// Action announced by `Kernel::plan_action`, expected at `t`; the next event
+// stepped at or after `t` uses it, so gaps in the stream do not drop it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    pub t: u64,
+    pub action: ActionParams,
+    #[serde(default)]
+    pub diagnostics: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepOutput {
+    pub trace: TraceRow,
+    pub replay: ReplayRow,
+}
+
+// Stateful engine: carries the belief state, memory window and policy across
+// events.
+#[derive(Clone, Debug)]
+pub struct Kernel {
+    pub cfg: KernelConfig,
+    pub mem: MemoryState,
+    pub policy: Box<dyn Policy>,
+    pub q_state: Option<Array1<f64>>,
+    pub a_tensor: Option<LikelihoodTensor>,
+    pub planned: Option<PlannedAction>,
+    // Likelihood columns and prior of the latest event, set once the action
+    // for it is chosen: the EFE policy's evidence about the next observation
+    // (the olfactory column only when no A tensor is loaded).
+    pub last_lik: Option<Vec<f64>>,
+    pub last_tact: Option<Vec<f64>>,
+    pub last_prior: Option<Vec<f64>>,
+}
+
+impl Kernel {
+    // Fails when `cfg.policy` names no built-in policy.
+    pub fn new(cfg: KernelConfig) -> Result<Self> {
+        let policy = PolicyRegistry::builtin().build(&cfg.policy, &cfg)?;
+        Ok(Self::with_policy(cfg, policy))
+    }
+
+    // Kernel driven by a policy built outside the registry; `cfg.policy` is
+    // then only a label.
+    pub fn with_policy(cfg: KernelConfig, policy: Box<dyn Policy>) -> Self {
+        let mut mem = match cfg.mem_mode {
+            MemoryMode::Window => MemoryState::new(cfg.mem_window),
+            MemoryMode::Decay => MemoryState::with_half_life(cfg.mem_half_life),
+        };
+        mem.last_k = cfg.mem_last_k;
+        Self {
+            cfg,
+            mem,
+            policy,
+            q_state: None,
+            a_tensor: None,
+            planned: None,
+            last_lik: None,
+            last_tact: None,
+            last_prior: None,
+        }
+    }
+
+    // Like `new`, but also loads the likelihood tensor named by `cfg.a_tensor`.
+    pub fn from_config(cfg: KernelConfig) -> Result<Self> {
+        let a_tensor = match &cfg.a_tensor {
+            Some(p) => Some(LikelihoodTensor::load(p)?),
+            None => None,
+        };
+        Ok(Self {
+            a_tensor,
+            ..Self::new(cfg)?
+        })
+    }
+
+    // Observation index and olfactory likelihood column for an event: sliced
+    // from the loaded A tensor, or taken from the event's own A_flat_col.
+    pub fn likelihood_column(&self, ev: &StreamEvent) -> Result<(usize, Vec<f64>)> {
+        let n = ev.p_prior.len();
+        match &self.a_tensor {
+            Some(a) => {
+                if !ev.a_flat_col.is_empty() {
+                    anyhow::bail!("event carries A_flat_col but a likelihood tensor is loaded");
+                }
+                if !ev.A_shape.is_empty() && ev.A_shape != a.shape {
+                    anyhow::bail!(
+                        "A_shape mismatch: tensor has {:?}, event says {:?}",
+                        a.shape,
+                        ev.A_shape
+                    );
+                }
+                if a.n_states() != n {
+                    anyhow::bail!(
+                        "A_shape mismatch: expected first dim {}, got {:?}",
+                        n,
+                        a.shape
+                    );
+                }
+                a.column(&ev.o)
+            }
+            None => {
+                if ev.A_shape.is_empty() || ev.A_shape[0] != n {
+                    anyhow::bail!(
+                        "A_shape mismatch: expected first dim {}, got {:?}",
+                        n,
+                        ev.A_shape
+                    );
+                }
+                let o_shape = &ev.A_shape[1..];
+                Ok((ravel_multi_index(&ev.o, o_shape)?, ev.a_flat_col.clone()))
+            }
+        }
+    }
+
+    // Prior handed to the Bayesian update (and to the free-energy terms).
+    pub fn prior_for_step(&self, q_before: &Array1<f64>, p_prior: &Array1<f64>) -> Array1<f64> {
+        step_prior(&self.cfg, q_before, p_prior)
+    }
+
+    // Closed loop: choose the action for the next event (expected at `t_next`)
+    // from the carried belief and memory, before that observation is taken.
+    // The next step uses it, whatever its `t` (>= t_next), unless the event
+    // reports its own action. None before the first step (no belief yet).
+    // The next event's `p_prior` is not known yet, so the latest one stands in.
+    pub fn plan_action(&mut self, t_next: u64, task: &[f64]) -> Option<ActionParams> {
+        let q = self.q_state.clone()?;
+        let p_prior = match &self.last_prior {
+            Some(p) => Array1::from(p.clone()),
+            None => q.clone(),
+        };
+        let mem = self.mem.features(t_next);
+        let task = Array1::from(task.to_vec());
+        let out = self.choose(&q, &p_prior, None, &mem, &task, t_next);
+        self.planned = Some(PlannedAction {
+            t: t_next,
+            action: out.action.clone(),
+            diagnostics: out.diagnostics,
+        });
+        Some(out.action)
+    }
+
+    // Runs the policy on what is known before the observation: the prior the
+    // update would use for each candidate action (after the predict step) and,
+    // as evidence, every column of the A tensor or else the previous event's
+    // columns.
+    fn choose(
+        &mut self,
+        q: &Array1<f64>,
+        p_prior: &Array1<f64>,
+        action_id: Option<usize>,
+        mem: &MemoryFeatures,
+        task: &Array1<f64>,
+        t: u64,
+    ) -> PolicyOutput {
+        let cfg = &self.cfg;
+        let prior = |a: &ActionParams| {
+            let q_before = match &cfg.transition {
+                None => q.clone(),
+                Some(tr) => match tr.select(action_id, a.sniff_strength, a.touch_pressure) {
+                    Ok(i) => tr.predict(i, q),
+                    Err(_) => q.clone(),
+                },
+            };
+            step_prior(cfg, &q_before, p_prior)
+        };
+        let columns = match &self.a_tensor {
+            Some(a) => a.columns(),
+            None => self.last_lik.iter().cloned().collect(),
+        };
+        self.policy.choose(&PolicyInput {
+            q_state: q,
+            mem,
+            task,
+            t,
+            forecast: Some(Forecast {
+                prior: &prior,
+                columns: &columns,
+                tactile: self.last_tact.as_deref(),
+                w_olf: cfg.w_olf,
+            }),
+        })
+    }
+
+    pub fn step(&mut self, ev: &StreamEvent) -> Result<StepOutput> {
+        let n = ev.p_prior.len();
+        let (o_idx, a_col) = self.likelihood_column(ev)?;
+        self.cfg.validate_for_belief(n)?;
+        // The update assumes every vector has the belief size, including the
+        // belief carried over from earlier events.
+        let sizes = [
+            ("likelihood column", Some(a_col.len())),
+            ("task_vec", Some(ev.task_vec.len())),
+            ("q0", ev.q0.as_ref().map(Vec::len)),
+            (
+                "tactile_flat_col",
+                ev.tactile_flat_col.as_ref().map(Vec::len),
+            ),
+            ("carried belief", self.q_state.as_ref().map(|q| q.len())),
+        ];
+        for (what, len) in sizes {
+            if let Some(len) = len.filter(|&len| len != n) {
+                anyhow::bail!("{} has length {}, expected belief size {}", what, len, n);
+            }
+        }
+        // Last check that can reject the event: nothing below may fail once
+        // the planned action is consumed or the policy has run.
+        if let Some(tr) = &self.cfg.transition {
+            tr.check_event(ev.action_id)?;
+        }
+
+        let p_prior = Array1::from(ev.p_prior.clone());
+        let task = Array1::from(ev.task_vec.clone());
+
+        let q_carried = match &self.q_state {
+            Some(q) => q.clone(),
+            None => Array1::from(ev.q0.clone().unwrap_or_else(|| p_prior.to_vec())),
+        };
+
+        let mem_feat_pre = self.mem.features(ev.t);
+        let planned = self.planned.take().filter(|p| ev.t >= p.t);
+
+        let (action, action_source, policy_diagnostics) =
+            match (ev.sniff_strength, ev.touch_pressure, planned) {
+                (Some(s), Some(tp), _) => (
+                    ActionParams {
+                        sniff_strength: s,
+                        touch_pressure: tp,
+                    },
+                    "event",
+                    None,
+                ),
+                (_, _, Some(p)) => (p.action, "planned", p.diagnostics),
+                _ => {
+                    let out = self.choose(
+                        &q_carried,
+                        &p_prior,
+                        ev.action_id,
+                        &mem_feat_pre,
+                        &task,
+                        ev.t,
+                    );
+                    (out.action, "policy", out.diagnostics)
+                }
+            };
+        let (sniff_strength, touch_pressure) = (action.sniff_strength, action.touch_pressure);
+        let policy = (action_source != "event").then(|| self.policy.name().to_string());
+
+        // Predict step: hidden causes may drift under the chosen action.
+        let (q_before, transition_idx) = match &self.cfg.transition {
+            None => (q_carried, None),
+            Some(tr) => {
+                let a = tr.select(ev.action_id, sniff_strength, touch_pressure)?;
+                (tr.predict(a, &q_carried), Some(a))
+            }
+        };
+
+        // This event's evidence is only for choosing later actions.
+        self.last_lik = Some(a_col.clone());
+        self.last_tact = ev.tactile_flat_col.clone();
+        self.last_prior = Some(ev.p_prior.clone());
+
+        let sensory = sensory_multimodal(
+            a_col,
+            ev.tactile_flat_col.clone(),
+            self.cfg.w_olf,
+            sniff_strength,
+            touch_pressure,
+        );
+        let lik_col = Array1::from(sensory.lik_mod.clone());
+
+        let prior = self.prior_for_step(&q_before, &p_prior);
+        let cycle = evaluate(&self.cfg, &q_before, &prior, &lik_col, &task);
+        self.q_state = Some(cycle.q_next.clone());
+
+        let survivor_levels = cycle.survivor_levels();
+        let (dx0, e0, p0, k0, eta0) = cycle.lead_msg();
+
+        let trace = TraceRow {
+            t: ev.t,
+            o_idx,
+            u_t: cycle.u_t,
+            g_before: cycle.g_before,
+            g_after_local: cycle.g_after_local,
+            g_after_broadcast: cycle.g_after_broadcast,
+            d_g_local: cycle.d_g_local,
+            d_g_broadcast: cycle.d_g_broadcast,
+            ignited: cycle.ignited,
+            ignite_reason: cycle.ignite_reason.clone(),
+            theta: cycle.theta,
+            survivors_n: cycle.survivors.len(),
+            coherence: cycle.coherence,
+            survivor_levels: survivor_levels.clone(),
+            q_after: cycle.q_after.to_vec(),
+            broadcast: cycle.broadcast.to_vec(),
+            dx: dx0.to_vec(),
+            e: e0,
+            p: p0,
+            k: k0,
+            eta: eta0,
+        };
+
+        self.mem.push(MemoryRow {
+            t: ev.t,
+            ignited: cycle.ignited,
+            d_g_broadcast: cycle.d_g_broadcast,
+            temperature: sensory.temperature,
+            sniff_strength,
+            touch_pressure,
+        });
+        let mem_feat_post = self.mem.features(ev.t);
+
+        let replay = ReplayRow {
+            t: ev.t,
+            o_idx,
+            sniff_strength,
+            touch_pressure,
+            action_source: action_source.to_string(),
+            policy,
+            policy_diagnostics,
+            temperature: sensory.temperature,
+            lik_olf: sensory.lik_raw,
+            lik_tact: sensory.lik_tact,
+            w_olf: sensory.w_olf,
+            lik_fused: sensory.lik_fused,
+            transition_idx,
+            prior: prior.to_vec(),
+            task_vec: ev.task_vec.clone(),
+            q_before: q_before.to_vec(),
+            q_after: cycle.q_after.to_vec(),
+            q_broadcast: cycle.q_broadcast.to_vec(),
+            q_next: cycle.q_next.to_vec(),
+            survivors_n: cycle.survivors.len(),
+            survivor_levels,
+            broadcast: cycle.broadcast.to_vec(),
+            b_expanded: cycle.b_expanded.to_vec(),
+            ignited: cycle.ignited,
+            ignite_reason: cycle.ignite_reason,
+            g_before: cycle.g_before,
+            g_after_local: cycle.g_after_local,
+            g_after_broadcast: cycle.g_after_broadcast,
+            d_g_local: cycle.d_g_local,
+            d_g_broadcast: cycle.d_g_broadcast,
+            mem_window_len: mem_feat_post.window_len,
+            mem_ignite_rate: mem_feat_post.ignite_rate,
+            mem_mean_d_g_broadcast: mem_feat_post.mean_d_g_broadcast,
+        };
+
+        Ok(StepOutput { trace, replay })
+    }
+}
@@ -1,6 +1,70 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{Result as IoResult, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::chain::{config_hash, HashChain};
+use crate::config::RunConfig;
+use crate::finite::check_finite;
+
+// 1: headerless rows. 2: header line, per-modality likelihood columns.
+// 3: replay rows record the update's `prior`. 4: and `transition_idx`.
+// 5: and `task_vec`, the last input `replay_verify` needs. 6: `policy` and
+// `policy_diagnostics`. 7: hash-chained ledgers end in a `LedgerTrailer` line.
+pub const LEDGER_SCHEMA_VERSION: u32 = 7;
+
+// First line of every ledger: schema version plus the resolved run-config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerHeader {
+    pub schema_version: u32,
+    pub ledger: String,
+    pub config: RunConfig,
+    // sha256 of the canonical config JSON; absent in ledgers written before it existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+}
+
+impl LedgerHeader {
+    pub fn new(ledger: &str, config: &RunConfig) -> Self {
+        Self {
+            schema_version: LEDGER_SCHEMA_VERSION,
+            ledger: ledger.to_string(),
+            config: config.clone(),
+            config_hash: config_hash(config).ok(),
+        }
+    }
+}
+
+// Last line of a hash-chained ledger, written by `LedgerWriter::finish` and
+// sealed into the chain: the row count (header excluded) and the hash of the
+// last row. Without it, a ledger cut after any row would still verify.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerTrailer {
+    pub trailer: bool,
+    pub rows: usize,
+    pub head_hash: String,
+}
+
+impl LedgerTrailer {
+    pub fn new(rows: usize, head_hash: &str) -> Self {
+        Self {
+            trailer: true,
+            rows,
+            head_hash: head_hash.to_string(),
+        }
+    }
+
+    // Whether a ledger line is a trailer rather than a row.
+    pub fn parse(line: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(line)
+            .ok()
+            .filter(|t| t.trailer)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraceRow {
@@ -35,9 +99,34 @@ pub struct ReplayRow {
     pub sniff_strength: f64,
     pub touch_pressure: f64,
     pub action_source: String,
+    // Name and diagnostics of the policy that chose the action (absent when
+    // the event carried it); the EFE policy lists every candidate's score.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_diagnostics: Option<serde_json::Value>,
 
     pub temperature: f64,
 
+    // Per-modality and fused likelihood columns (before temperature modulation).
+    // Schema v2; absent in v1 ledgers.
+    #[serde(default)]
+    pub lik_olf: Vec<f64>,
+    #[serde(default)]
+    pub lik_tact: Option<Vec<f64>>,
+    #[serde(default)]
+    pub w_olf: Option<f64>,
+    #[serde(default)]
+    pub lik_fused: Vec<f64>,
+
+    // Transition matrix applied before the update, if a transition model is configured.
+    #[serde(default)]
+    pub transition_idx: Option<usize>,
+    // Prior actually used by the update (p_prior, or the carried belief when filtering).
+    #[serde(default)]
+    pub prior: Vec<f64>,
+    #[serde(default)]
+    pub task_vec: Vec<f64>,
     pub q_before: Vec<f64>,
     pub q_after: Vec<f64>,
     pub q_broadcast: Vec<f64>,
@@ -62,7 +151,431 @@ pub struct ReplayRow {
     pub mem_mean_d_g_broadcast: f64,
 }
 
-pub fn ndjson_write_row<T: Serialize>(f: &mut File, row: &T) -> IoResult<()> {
-    let s = serde_json::to_string(row).unwrap();
-    writeln!(f, "{}", s)
+// One row as a JSON line. Non-finite floats are rejected rather than written
+// as `null`.
+pub fn ndjson_write_row<W: Write, T: Serialize>(w: &mut W, row: &T) -> Result<()> {
+    check_finite(row)?;
+    serde_json::to_writer(&mut *w, row)?;
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriterOptions {
+    // Flush the buffer every N rows; 0 flushes only on `flush` / `finish`.
+    pub flush_every: usize,
+    // fsync the file (and its directory) when finishing.
+    pub fsync: bool,
+}
+
+// Buffered ledger writer. Rows go to `<path>.partial`, which `finish` renames
+// to `path`, so an interrupted run never leaves a complete-looking ledger.
+// With `hash_chain` set in the config, every line (header included) is sealed
+// into a hash chain, and `finish` closes it with a `LedgerTrailer`.
+pub struct LedgerWriter {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    out: BufWriter<File>,
+    chain: Option<HashChain>,
+    opts: WriterOptions,
+    rows: usize,
+}
+
+impl LedgerWriter {
+    pub fn create(path: &Path, header: &LedgerHeader) -> Result<Self> {
+        Self::create_with(path, header, WriterOptions::default())
+    }
+
+    pub fn create_with(path: &Path, header: &LedgerHeader, opts: WriterOptions) -> Result<Self> {
+        let tmp_path = partial_path(path);
+        let file =
+            File::create(&tmp_path).with_context(|| format!("creating {}", tmp_path.display()))?;
+        let mut w = Self {
+            path: path.to_path_buf(),
+            tmp_path,
+            out: BufWriter::new(file),
+            chain: header.config.hash_chain.then(HashChain::new),
+            opts,
+            rows: 0,
+        };
+        w.write_line(header).context("writing ledger header")?;
+        Ok(w)
+    }
+
+    // Reopen a ledger for appending after its first `rows` rows, dropping any
+    // rows written past that point. Picks up `<path>.partial` from an
+    // interrupted run, or moves a finished ledger back to `.partial`; the
+    // trailer of a finished chained ledger is dropped with the extra rows.
+    pub fn resume(
+        path: &Path,
+        config: &RunConfig,
+        rows: usize,
+        opts: WriterOptions,
+    ) -> Result<Self> {
+        let tmp_path = partial_path(path);
+        if !tmp_path.exists() {
+            std::fs::rename(path, &tmp_path)
+                .with_context(|| format!("reopening {}", path.display()))?;
+        }
+
+        let mut reader = BufReader::new(
+            File::open(&tmp_path).with_context(|| format!("opening {}", tmp_path.display()))?,
+        );
+        let mut keep = 0u64;
+        let mut line = String::new();
+        let mut last = String::new();
+        for i in 0..=rows {
+            line.clear();
+            let n = reader.read_line(&mut line)?;
+            // A finished chained ledger ends in a trailer, which is not a row.
+            if n == 0 || !line.ends_with('\n') || (i > 0 && LedgerTrailer::parse(&line).is_some()) {
+                anyhow::bail!(
+                    "{} holds {} complete rows, checkpoint expects {}",
+                    tmp_path.display(),
+                    i.saturating_sub(1),
+                    rows
+                );
+            }
+            if i == 0 {
+                let header: LedgerHeader = serde_json::from_str(&line)
+                    .with_context(|| format!("{}: bad ledger header", tmp_path.display()))?;
+                // Appending rows of another layout would mix schemas in one file.
+                if header.schema_version != LEDGER_SCHEMA_VERSION {
+                    anyhow::bail!(
+                        "{} has schema v{}, this build writes v{}",
+                        tmp_path.display(),
+                        header.schema_version,
+                        LEDGER_SCHEMA_VERSION
+                    );
+                }
+                if header.config_hash != Some(config_hash(config)?) {
+                    anyhow::bail!(
+                        "{} was written with a different run-config",
+                        tmp_path.display()
+                    );
+                }
+            }
+            keep += n as u64;
+            std::mem::swap(&mut last, &mut line);
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .open(&tmp_path)
+            .with_context(|| format!("opening {}", tmp_path.display()))?;
+        file.set_len(keep)?;
+        let file = OpenOptions::new().append(true).open(&tmp_path)?;
+
+        let chain = if config.hash_chain {
+            let v: serde_json::Value = serde_json::from_str(&last)?;
+            let head = v
+                .get("hash")
+                .and_then(|h| h.as_str())
+                .ok_or_else(|| anyhow::anyhow!("{} is not hash-chained", tmp_path.display()))?;
+            Some(HashChain::from_head(head))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp_path,
+            out: BufWriter::new(file),
+            chain,
+            opts,
+            rows,
+        })
+    }
+
+    // Rows written so far, header excluded.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn write_row<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        self.write_line(row)
+            .with_context(|| format!("writing row {} of {}", self.rows + 1, self.path.display()))?;
+        self.rows += 1;
+        if self.opts.flush_every > 0 && self.rows.is_multiple_of(self.opts.flush_every) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn write_line<T: Serialize>(&mut self, row: &T) -> Result<()> {
+        match self.chain.as_mut() {
+            Some(chain) => {
+                check_finite(row)?;
+                let sealed = chain.seal(row)?;
+                ndjson_write_row(&mut self.out, &sealed)
+            }
+            None => ndjson_write_row(&mut self.out, row),
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.out
+            .flush()
+            .with_context(|| format!("flushing {}", self.tmp_path.display()))
+    }
+
+    // Flush, then fsync if the writer was opened with `fsync`.
+    pub fn sync(&mut self) -> Result<()> {
+        self.flush()?;
+        if self.opts.fsync {
+            self.out.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    // Seal the trailer (chained ledgers), flush, optionally fsync, and move the
+    // ledger into place.
+    pub fn finish(mut self) -> Result<PathBuf> {
+        if let Some(chain) = &self.chain {
+            let trailer = LedgerTrailer::new(self.rows, chain.last_hash());
+            self.write_line(&trailer)
+                .with_context(|| format!("writing trailer of {}", self.path.display()))?;
+        }
+        self.flush()?;
+        if self.opts.fsync {
+            self.out.get_ref().sync_all()?;
+        }
+        std::fs::rename(&self.tmp_path, &self.path).with_context(|| {
+            format!(
+                "renaming {} to {}",
+                self.tmp_path.display(),
+                self.path.display()
+            )
+        })?;
+        if self.opts.fsync {
+            if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                File::open(dir)?.sync_all()?;
+            }
+        }
+        Ok(self.path)
+    }
+
+    // Abandon the ledger: removes `<path>.partial` and leaves `path` untouched.
+    pub fn discard(self) -> Result<()> {
+        let Self { tmp_path, out, .. } = self;
+        drop(out);
+        std::fs::remove_file(&tmp_path).with_context(|| format!("removing {}", tmp_path.display()))
+    }
+}
+
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+// Rows that can be streamed back out of a ledger file.
+pub trait LedgerRow: DeserializeOwned {
+    const KIND: &'static str;
+    fn t(&self) -> u64;
+    fn ignite_reason(&self) -> &str;
+}
+
+impl LedgerRow for TraceRow {
+    const KIND: &'static str = "trace";
+    fn t(&self) -> u64 {
+        self.t
+    }
+    fn ignite_reason(&self) -> &str {
+        &self.ignite_reason
+    }
+}
+
+impl LedgerRow for ReplayRow {
+    const KIND: &'static str = "replay";
+    fn t(&self) -> u64 {
+        self.t
+    }
+    fn ignite_reason(&self) -> &str {
+        &self.ignite_reason
+    }
+}
+
+// Kind ("trace" / "replay") recorded in a ledger's header; None if headerless.
+pub fn ledger_kind(path: &Path) -> Result<Option<String>> {
+    let f = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    for line in BufReader::new(f).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let v: Option<serde_json::Value> = serde_json::from_str(&line).ok();
+        return Ok(v
+            .filter(|v| v.get("schema_version").is_some())
+            .and_then(|v| v.get("ledger")?.as_str().map(str::to_string)));
+    }
+    Ok(None)
+}
+
+// Row selection by inclusive `t` range and ignite_reason.
+#[derive(Debug, Clone, Default)]
+pub struct RowFilter {
+    pub t_min: Option<u64>,
+    pub t_max: Option<u64>,
+    pub ignite_reason: Option<String>,
+}
+
+impl RowFilter {
+    pub fn matches<T: LedgerRow>(&self, row: &T) -> bool {
+        self.t_min.is_none_or(|m| row.t() >= m)
+            && self.t_max.is_none_or(|m| row.t() <= m)
+            && self
+                .ignite_reason
+                .as_deref()
+                .is_none_or(|r| row.ignite_reason() == r)
+    }
+}
+
+// Streaming reader over a trace or replay ledger.
+// Ledgers without a header line are schema v1. A final line that does not
+// parse is taken as a truncated write and ends the stream (see `truncated`);
+// bad lines anywhere else are errors. The trailer of a chained ledger is not
+// a row (see `trailer`).
+pub struct LedgerReader<R: BufRead, T> {
+    lines: Peekable<Lines<R>>,
+    line_no: usize,
+    header: Option<LedgerHeader>,
+    pending: Option<(usize, String)>,
+    filter: RowFilter,
+    truncated: bool,
+    trailer: Option<LedgerTrailer>,
+    _row: PhantomData<T>,
+}
+
+impl<T: LedgerRow> LedgerReader<BufReader<File>, T> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let f = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        Self::new(BufReader::new(f)).with_context(|| format!("reading {}", path.display()))
+    }
+}
+
+impl<R: BufRead, T: LedgerRow> LedgerReader<R, T> {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut me = Self {
+            lines: reader.lines().peekable(),
+            line_no: 0,
+            header: None,
+            pending: None,
+            filter: RowFilter::default(),
+            truncated: false,
+            trailer: None,
+            _row: PhantomData,
+        };
+        if let Some((line_no, line)) = me.next_line()? {
+            let v: Option<serde_json::Value> = serde_json::from_str(&line).ok();
+            if v.as_ref()
+                .is_some_and(|v| v.get("schema_version").is_some())
+            {
+                let h: LedgerHeader = serde_json::from_value(v.unwrap())
+                    .with_context(|| format!("line {}: bad ledger header", line_no))?;
+                if h.schema_version > LEDGER_SCHEMA_VERSION {
+                    anyhow::bail!(
+                        "ledger schema v{} is newer than supported v{}",
+                        h.schema_version,
+                        LEDGER_SCHEMA_VERSION
+                    );
+                }
+                if h.ledger != T::KIND {
+                    anyhow::bail!("expected a {} ledger, found {}", T::KIND, h.ledger);
+                }
+                me.header = Some(h);
+            } else {
+                me.pending = Some((line_no, line));
+            }
+        }
+        Ok(me)
+    }
+
+    pub fn with_filter(mut self, filter: RowFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn header(&self) -> Option<&LedgerHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn schema_version(&self) -> u32 {
+        self.header.as_ref().map(|h| h.schema_version).unwrap_or(1)
+    }
+
+    // True once the reader has dropped an unparsable final line.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // The trailer, once the reader has reached it (chained ledgers only).
+    pub fn trailer(&self) -> Option<&LedgerTrailer> {
+        self.trailer.as_ref()
+    }
+
+    fn next_line(&mut self) -> Result<Option<(usize, String)>> {
+        if let Some(p) = self.pending.take() {
+            return Ok(Some(p));
+        }
+        for line in self.lines.by_ref() {
+            self.line_no += 1;
+            let line = line.with_context(|| format!("line {}", self.line_no))?;
+            if !line.trim().is_empty() {
+                return Ok(Some((self.line_no, line)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn at_eof(&mut self) -> bool {
+        loop {
+            match self.lines.peek() {
+                None => return true,
+                Some(Ok(l)) if l.trim().is_empty() => {
+                    self.lines.next();
+                    self.line_no += 1;
+                }
+                Some(_) => return false,
+            }
+        }
+    }
+
+    // A line that is neither a row nor the trailer.
+    fn bad_line(&mut self, line_no: usize, e: serde_json::Error) -> Option<Result<T>> {
+        if self.at_eof() {
+            self.truncated = true;
+            return None;
+        }
+        Some(Err(
+            anyhow::Error::from(e).context(format!("line {}", line_no))
+        ))
+    }
+}
+
+impl<R: BufRead, T: LedgerRow> Iterator for LedgerReader<R, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (line_no, line) = match self.next_line() {
+                Ok(Some(x)) => x,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let row: T = match serde_json::from_str(&line) {
+                Ok(r) => r,
+                Err(e) => match LedgerTrailer::parse(&line) {
+                    Some(t) => {
+                        self.trailer = Some(t);
+                        continue;
+                    }
+                    None => return self.bad_line(line_no, e),
+                },
+            };
+            if self.filter.matches(&row) {
+                return Some(Ok(row));
+            }
+        }
+    }
 }
@@ -2,8 +2,23 @@ pub mod adapter;
 pub mod ignition;
 
 pub mod broadcast;
+pub mod chain;
+pub mod checkpoint;
+pub mod config;
+pub mod diff;
+pub mod event;
+pub mod export;
+pub mod finite;
+pub mod flatten;
+pub mod kernel;
 pub mod ledger;
+pub mod likelihood;
 pub mod memory;
 pub mod policy;
+pub mod replay;
 pub mod sensory;
+pub mod stream;
+pub mod summary;
+pub mod transition;
 pub mod util;
+pub mod validate;
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::util::ravel_multi_index;
+
+const NORM_TOL: f64 = 1e-6;
+
+// Full likelihood tensor A with shape [n_states, obs dims...], row-major.
+// A[s, o...] = p(o | s), so every state's slice sums to 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LikelihoodTensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<f64>,
+}
+
+impl LikelihoodTensor {
+    // `.npy` files are read as NumPy arrays; anything else as JSON {"shape", "data"}.
+    pub fn load(path: &Path) -> Result<Self> {
+        let is_npy = path
+            .extension()
+            .map(|e| e.eq_ignore_ascii_case("npy"))
+            .unwrap_or(false);
+        let a = if is_npy {
+            let bytes =
+                std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+            Self::from_npy(&bytes).with_context(|| format!("parsing NPY {}", path.display()))?
+        } else {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            serde_json::from_str(&text)
+                .with_context(|| format!("parsing JSON {}", path.display()))?
+        };
+        a.validate()
+            .with_context(|| format!("invalid likelihood tensor {}", path.display()))?;
+        Ok(a)
+    }
+
+    // NPY v1-v3, little-endian f8/f4, C order.
+    pub fn from_npy(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 10 || &bytes[0..6] != b"\x93NUMPY" {
+            anyhow::bail!("missing NPY magic");
+        }
+        let major = bytes[6];
+        let (header_len, start) = match major {
+            1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            2 | 3 => {
+                if bytes.len() < 12 {
+                    anyhow::bail!("truncated NPY header");
+                }
+                let n = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+                (n as usize, 12)
+            }
+            v => anyhow::bail!("unsupported NPY version {}", v),
+        };
+        let end = start + header_len;
+        if bytes.len() < end {
+            anyhow::bail!("truncated NPY header");
+        }
+        let header = std::str::from_utf8(&bytes[start..end])?;
+
+        let descr = npy_header_value(header, "descr")?;
+        let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+        let width = match descr {
+            "<f8" | "=f8" => 8,
+            "<f4" | "=f4" => 4,
+            other => anyhow::bail!("unsupported dtype {}", other),
+        };
+        if npy_header_value(header, "fortran_order")? != "False" {
+            anyhow::bail!("fortran_order arrays are not supported");
+        }
+        let shape_src = npy_header_value(header, "shape")?;
+        let shape = shape_src
+            .trim_matches(|c| c == '(' || c == ')')
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<usize>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .with_context(|| format!("bad shape {}", shape_src))?;
+
+        let count: usize = shape.iter().product();
+        let body = &bytes[end..];
+        if body.len() != count * width {
+            anyhow::bail!(
+                "NPY body has {} bytes, expected {} for shape {:?}",
+                body.len(),
+                count * width,
+                shape
+            );
+        }
+        let data = if width == 8 {
+            body.chunks_exact(8)
+                .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+                .collect()
+        } else {
+            body.chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()) as f64)
+                .collect()
+        };
+        Ok(Self { shape, data })
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.shape.first().copied().unwrap_or(0)
+    }
+
+    pub fn obs_shape(&self) -> &[usize] {
+        self.shape.get(1..).unwrap_or(&[])
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.shape.len() < 2 || self.shape.contains(&0) {
+            anyhow::bail!(
+                "shape must be [n_states, obs dims...] with no zero dims, got {:?}",
+                self.shape
+            );
+        }
+        let n_obs: usize = self.obs_shape().iter().product();
+        if self.data.len() != self.n_states() * n_obs {
+            anyhow::bail!(
+                "data has {} entries, shape {:?} needs {}",
+                self.data.len(),
+                self.shape,
+                self.n_states() * n_obs
+            );
+        }
+        for (s, row) in self.data.chunks_exact(n_obs).enumerate() {
+            if let Some(v) = row.iter().find(|v| !v.is_finite() || **v < 0.0) {
+                anyhow::bail!("state {} has invalid probability {}", s, v);
+            }
+            let z: f64 = row.iter().sum();
+            if (z - 1.0).abs() > NORM_TOL {
+                anyhow::bail!("state {} distribution sums to {}, expected 1", s, z);
+            }
+        }
+        Ok(())
+    }
+
+    // Every observation's likelihood column, in flat observation order.
+    pub fn columns(&self) -> Vec<Vec<f64>> {
+        let n_obs: usize = self.obs_shape().iter().product();
+        (0..n_obs)
+            .map(|o| {
+                (0..self.n_states())
+                    .map(|s| self.data[s * n_obs + o])
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Likelihood column p(o | s) over all states.
+    pub fn column(&self, o: &[usize]) -> Result<(usize, Vec<f64>)> {
+        let obs_shape = self.obs_shape();
+        let o_idx = ravel_multi_index(o, obs_shape)?;
+        let n_obs: usize = obs_shape.iter().product();
+        let col = (0..self.n_states())
+            .map(|s| self.data[s * n_obs + o_idx])
+            .collect();
+        Ok((o_idx, col))
+    }
+}
+
+// Raw value of `key` in a NPY header dict, e.g. "'<f8'" or "(4, 3, 5)".
+fn npy_header_value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let pat_sq = format!("'{}':", key);
+    let pat_dq = format!("\"{}\":", key);
+    let pos = header
+        .find(&pat_sq)
+        .map(|p| p + pat_sq.len())
+        .or_else(|| header.find(&pat_dq).map(|p| p + pat_dq.len()))
+        .ok_or_else(|| anyhow::anyhow!("NPY header has no {:?}", key))?;
+    let rest = header[pos..].trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }
+    .ok_or_else(|| anyhow::anyhow!("unterminated {:?} in NPY header", key))?;
+    Ok(rest[..end].trim())
+}
@@ -1,19 +1,61 @@
 use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::VecDeque;
 
 const EPS: f64 = 1e-9;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// Version of the `MemoryFeatures` layout handed to policies through
+// `MemoryStats::mem_features`. 1: means only; 2: adds spread, trend and the
+// last-k block; 3: adds `effective_n`.
+pub const MEMORY_FEATURES_VERSION: u32 = 3;
+
+// Rows the last-k statistics look back over unless configured.
+pub const DEFAULT_LAST_K: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MemoryFeatures {
+    pub version: u32,
     pub t: u64,
+    // Rows contributing to the statistics (every row seen, in decay mode).
     pub window_len: usize,
+    // Effective sample size (sum w)^2 / sum w^2: window_len for the sliding
+    // window, about 2.9 * half-life once a decaying memory has filled.
+    pub effective_n: f64,
     pub ignite_rate: f64,
     pub mean_d_g_broadcast: f64,
     pub mean_temperature: f64,
     pub mean_sniff_strength: f64,
     pub mean_touch_pressure: f64,
+
+    // Population variances over the window (E[x^2] - E[x]^2, floored at 0).
+    pub var_d_g_broadcast: f64,
+    pub var_temperature: f64,
+    pub var_sniff_strength: f64,
+    pub var_touch_pressure: f64,
+    // Least-squares slope per row over the window; 0 with fewer than 2 rows.
+    pub trend_d_g_broadcast: f64,
+    pub trend_sniff_strength: f64,
+    pub trend_touch_pressure: f64,
+
+    pub recent: RecentFeatures,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// Statistics of the newest `k` rows (fewer while the window fills up).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecentFeatures {
+    pub k: usize,
+    pub ignite_rate: f64,
+    pub mean_sniff_strength: f64,
+    pub mean_touch_pressure: f64,
+    // Direction changes of consecutive action moves (flat moves are skipped):
+    // a high count means the actions oscillate.
+    pub sniff_reversals: usize,
+    pub touch_reversals: usize,
+    pub last_sniff_strength: Option<f64>,
+    pub last_touch_pressure: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MemoryRow {
     pub t: u64,
     pub ignited: bool,
@@ -23,51 +65,366 @@ pub struct MemoryRow {
     pub touch_pressure: f64,
 }
 
+// How old rows fade out of the memory statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryMode {
+    // Plain means over the last `mem_window` rows.
+    #[default]
+    Window,
+    // Every row counts, weighted by 0.5^(age / mem_half_life).
+    Decay,
+}
+
+// Weighted sums of one column. Ages count rows pushed since (0 = newest).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DecayMoments {
+    pub sum: f64,
+    pub sum_sq: f64,
+    pub sum_age_x: f64,
+}
+
+// Exponentially decayed statistics, updated in O(1) per row: each push scales
+// every sum by `lambda` = 0.5^(1 / half_life) and adds the new row at age 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecaySums {
+    pub lambda: f64,
+    pub n_seen: usize,
+    pub w: f64,
+    pub w_sq: f64,
+    pub w_age: f64,
+    pub w_age_sq: f64,
+    pub ignited: f64,
+    // d_g_broadcast, temperature, sniff_strength, touch_pressure.
+    pub cols: [DecayMoments; 4],
+}
+
+impl DecaySums {
+    pub fn new(half_life: f64) -> Self {
+        Self {
+            lambda: 0.5f64.powf(1.0 / half_life),
+            n_seen: 0,
+            w: 0.0,
+            w_sq: 0.0,
+            w_age: 0.0,
+            w_age_sq: 0.0,
+            ignited: 0.0,
+            cols: [DecayMoments::default(); 4],
+        }
+    }
+
+    // Back to no rows, same half-life.
+    pub fn reset(&mut self) {
+        *self = Self {
+            lambda: self.lambda,
+            ..Self::new(1.0)
+        };
+    }
+
+    pub fn push(&mut self, row: &MemoryRow) {
+        let l = self.lambda;
+        // Existing rows age by one: sum w(a+1) = sum wa + sum w, and so on.
+        for (m, x) in self.cols.iter_mut().zip(columns(row)) {
+            m.sum_age_x = l * (m.sum_age_x + m.sum);
+            m.sum = l * m.sum + x;
+            m.sum_sq = l * m.sum_sq + x * x;
+        }
+        self.w_age_sq = l * (self.w_age_sq + 2.0 * self.w_age + self.w);
+        self.w_age = l * (self.w_age + self.w);
+        self.w = l * self.w + 1.0;
+        self.w_sq = l * l * self.w_sq + 1.0;
+        self.ignited = l * self.ignited + if row.ignited { 1.0 } else { 0.0 };
+        self.n_seen += 1;
+    }
+
+    fn mean_var(&self, i: usize) -> (f64, f64) {
+        let w = self.w.max(EPS);
+        let m = &self.cols[i];
+        let mean = m.sum / w;
+        (mean, (m.sum_sq / w - mean * mean).max(0.0))
+    }
+
+    // Weighted least-squares slope per row (newer rows have lower age, hence
+    // the sign flip).
+    fn trend(&self, i: usize) -> f64 {
+        let m = &self.cols[i];
+        let den = self.w * self.w_age_sq - self.w_age * self.w_age;
+        if self.n_seen < 2 || den.abs() <= EPS {
+            return 0.0;
+        }
+        -(self.w * m.sum_age_x - self.w_age * m.sum) / den
+    }
+
+    pub fn effective_n(&self) -> f64 {
+        if self.w_sq > 0.0 {
+            self.w * self.w / self.w_sq
+        } else {
+            0.0
+        }
+    }
+
+    pub fn ignite_rate(&self) -> f64 {
+        self.ignited / self.w.max(EPS)
+    }
+}
+
+// Columns with running sums: d_g_broadcast, temperature, sniff_strength,
+// touch_pressure.
+fn columns(r: &MemoryRow) -> [f64; 4] {
+    [
+        r.d_g_broadcast,
+        r.temperature,
+        r.sniff_strength,
+        r.touch_pressure,
+    ]
+}
+
+// Window sums of one column, accumulated front to back exactly as the
+// per-call scans did: `total` is `Iterator::sum` (which starts from -0.0) for
+// the mean, the rest are folds from 0.0 for the variance and trend; `sum_ix`
+// weights each value by its index in the window (0 = oldest).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct WindowMoments {
+    total: f64,
+    sum: f64,
+    sum_sq: f64,
+    sum_ix: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct WindowSums {
+    cols: [WindowMoments; 4],
+}
+
+impl Default for WindowSums {
+    fn default() -> Self {
+        let empty = WindowMoments {
+            total: -0.0,
+            ..WindowMoments::default()
+        };
+        Self { cols: [empty; 4] }
+    }
+}
+
+impl WindowSums {
+    fn scan<'a>(rows: impl Iterator<Item = &'a MemoryRow>) -> Self {
+        let mut sums = Self::default();
+        for (i, r) in rows.enumerate() {
+            sums.add(r, i);
+        }
+        sums
+    }
+
+    // The next step of the scan: `index` must be the row's place in the window.
+    fn add(&mut self, row: &MemoryRow, index: usize) {
+        let i = index as f64;
+        for (m, x) in self.cols.iter_mut().zip(columns(row)) {
+            m.total += x;
+            m.sum += x;
+            m.sum_sq += x * x;
+            m.sum_ix += i * x;
+        }
+    }
+
+    fn mean(&self, i: usize, n: f64) -> f64 {
+        self.cols[i].total / n
+    }
+
+    fn var(&self, i: usize, n: f64) -> f64 {
+        let m = &self.cols[i];
+        let mean = m.sum / n;
+        (m.sum_sq / n - mean * mean).max(0.0)
+    }
+
+    // Slope of the column against its window index; 0 with fewer than 2 rows.
+    fn trend(&self, i: usize, len: usize) -> f64 {
+        if len < 2 {
+            return 0.0;
+        }
+        let m = &self.cols[i];
+        let n = len as f64;
+        let sum_i = n * (n - 1.0) / 2.0;
+        let sum_ii = (n - 1.0) * n * (2.0 * n - 1.0) / 6.0;
+        (n * m.sum_ix - sum_i * m.sum) / (n * sum_ii - sum_i * sum_i)
+    }
+}
+
+// Rows live in a ring buffer (`VecDeque`), so `push` is O(1) in the window
+// size. The window statistics are the front-to-back scan they have always
+// been, bit for bit: the sums grow in place while the window fills, and after
+// an eviction the first read rescans the window once and caches it for the
+// rest of the step. The integer ignite count is kept running.
 #[derive(Clone, Debug)]
 pub struct MemoryState {
-    pub window_max: usize,
-    pub rows: Vec<MemoryRow>,
+    // Private: the window contents are only valid for the size they were
+    // built with (see `window_max()`).
+    window_max: usize,
+    pub last_k: usize,
+    // Decay mode: statistics come from these sums and `rows` keeps only the
+    // last-k rows.
+    pub decay: Option<DecaySums>,
+    rows: VecDeque<MemoryRow>,
+    ignited: usize,
+    sums: OnceCell<WindowSums>,
+}
+
+fn reversals(xs: impl Iterator<Item = f64>) -> usize {
+    let mut prev_x: Option<f64> = None;
+    let mut prev_dir = 0.0;
+    let mut n = 0;
+    for x in xs {
+        if let Some(p) = prev_x {
+            let d = x - p;
+            if d.abs() > EPS {
+                if prev_dir * d < 0.0 {
+                    n += 1;
+                }
+                prev_dir = d;
+            }
+        }
+        prev_x = Some(x);
+    }
+    n
 }
 
 impl MemoryState {
     pub fn new(window_max: usize) -> Self {
         Self {
             window_max,
-            rows: Vec::new(),
+            last_k: DEFAULT_LAST_K,
+            decay: None,
+            rows: VecDeque::new(),
+            ignited: 0,
+            sums: OnceCell::from(WindowSums::default()),
+        }
+    }
+
+    pub fn with_half_life(half_life: f64) -> Self {
+        Self {
+            decay: Some(DecaySums::new(half_life)),
+            ..Self::new(DEFAULT_LAST_K)
+        }
+    }
+
+    pub fn window_max(&self) -> usize {
+        self.window_max
+    }
+
+    // Retained rows, oldest first: the window, or the last-k rows in decay mode.
+    pub fn rows(&self) -> &VecDeque<MemoryRow> {
+        &self.rows
+    }
+
+    // Forget every row (decay sums included).
+    pub fn clear(&mut self) {
+        self.rows.clear();
+        self.ignited = 0;
+        self.sums = OnceCell::from(WindowSums::default());
+        if let Some(d) = &mut self.decay {
+            d.reset();
+        }
+    }
+
+    // Rows retained: the window, or just the last-k block in decay mode.
+    fn capacity(&self) -> usize {
+        match self.decay {
+            Some(_) => self.last_k,
+            None => self.window_max,
         }
     }
 
     pub fn push(&mut self, row: MemoryRow) {
-        self.rows.push(row);
-        if self.rows.len() > self.window_max {
-            let overflow = self.rows.len() - self.window_max;
-            self.rows.drain(0..overflow);
+        match &mut self.decay {
+            Some(d) => d.push(&row),
+            None => {
+                if let Some(sums) = self.sums.get_mut() {
+                    sums.add(&row, self.rows.len());
+                }
+                self.ignited += row.ignited as usize;
+            }
+        }
+        self.rows.push_back(row);
+        while self.rows.len() > self.capacity() {
+            let old = self.rows.pop_front().expect("len > capacity");
+            if self.decay.is_none() {
+                self.ignited -= old.ignited as usize;
+                // Every index shifts and the scan starts at a new row.
+                self.sums.take();
+            }
         }
     }
 
+    fn window_sums(&self) -> &WindowSums {
+        self.sums.get_or_init(|| WindowSums::scan(self.rows.iter()))
+    }
+
     pub fn features(&self, t: u64) -> MemoryFeatures {
-        let n = self.rows.len().max(1) as f64;
-        let ignite_rate = self.rows.iter().filter(|r| r.ignited).count() as f64 / n;
+        if let Some(d) = &self.decay {
+            let mv = |i| d.mean_var(i);
+            return MemoryFeatures {
+                version: MEMORY_FEATURES_VERSION,
+                t,
+                window_len: d.n_seen,
+                effective_n: d.effective_n(),
+                ignite_rate: d.ignite_rate(),
+                mean_d_g_broadcast: mv(0).0,
+                mean_temperature: mv(1).0,
+                mean_sniff_strength: mv(2).0,
+                mean_touch_pressure: mv(3).0,
+                var_d_g_broadcast: mv(0).1,
+                var_temperature: mv(1).1,
+                var_sniff_strength: mv(2).1,
+                var_touch_pressure: mv(3).1,
+                trend_d_g_broadcast: d.trend(0),
+                trend_sniff_strength: d.trend(2),
+                trend_touch_pressure: d.trend(3),
+                recent: self.recent(),
+            };
+        }
 
-        let mean_d_g_broadcast = self.rows.iter().map(|r| r.d_g_broadcast).sum::<f64>() / n;
-        let mean_temperature = self.rows.iter().map(|r| r.temperature).sum::<f64>() / n;
-        let mean_sniff_strength = self.rows.iter().map(|r| r.sniff_strength).sum::<f64>() / n;
-        let mean_touch_pressure = self.rows.iter().map(|r| r.touch_pressure).sum::<f64>() / n;
+        let len = self.rows.len();
+        let n = len.max(1) as f64;
+        let sums = self.window_sums();
 
         MemoryFeatures {
+            version: MEMORY_FEATURES_VERSION,
             t,
-            window_len: self.rows.len(),
-            ignite_rate,
-            mean_d_g_broadcast,
-            mean_temperature,
-            mean_sniff_strength,
-            mean_touch_pressure,
+            window_len: len,
+            effective_n: len as f64,
+            ignite_rate: self.ignited as f64 / n,
+            mean_d_g_broadcast: sums.mean(0, n),
+            mean_temperature: sums.mean(1, n),
+            mean_sniff_strength: sums.mean(2, n),
+            mean_touch_pressure: sums.mean(3, n),
+            var_d_g_broadcast: sums.var(0, n),
+            var_temperature: sums.var(1, n),
+            var_sniff_strength: sums.var(2, n),
+            var_touch_pressure: sums.var(3, n),
+            trend_d_g_broadcast: sums.trend(0, len),
+            trend_sniff_strength: sums.trend(2, len),
+            trend_touch_pressure: sums.trend(3, len),
+            recent: self.recent(),
         }
     }
 
-    pub fn decay_hint(&self) -> f64 {
-        let n = self.rows.len() as f64;
-        1.0 / (1.0 + (n / 16.0).max(EPS))
+    pub fn recent(&self) -> RecentFeatures {
+        let tail = || {
+            self.rows
+                .range(self.rows.len().saturating_sub(self.last_k)..)
+        };
+        let k = tail().len();
+        let n = k.max(1) as f64;
+        let last = self.rows.back();
+        RecentFeatures {
+            k,
+            ignite_rate: tail().filter(|r| r.ignited).count() as f64 / n,
+            mean_sniff_strength: tail().map(|r| r.sniff_strength).sum::<f64>() / n,
+            mean_touch_pressure: tail().map(|r| r.touch_pressure).sum::<f64>() / n,
+            sniff_reversals: reversals(tail().map(|r| r.sniff_strength)),
+            touch_reversals: reversals(tail().map(|r| r.touch_pressure)),
+            last_sniff_strength: last.map(|r| r.sniff_strength),
+            last_touch_pressure: last.map(|r| r.touch_pressure),
+        }
     }
 }
 
@@ -75,17 +432,24 @@ use crate::policy::MemoryStats;
 
 impl MemoryState {
     pub fn mem_window_len(&self) -> usize {
-        self.rows.len()
+        match &self.decay {
+            Some(d) => d.n_seen,
+            None => self.rows.len(),
+        }
     }
 
     pub fn mem_ignite_rate(&self) -> f64 {
-        let n = self.rows.len().max(1) as f64;
-        self.rows.iter().filter(|r| r.ignited).count() as f64 / n
+        match &self.decay {
+            Some(d) => d.ignite_rate(),
+            None => self.ignited as f64 / self.rows.len().max(1) as f64,
+        }
     }
 
     pub fn mem_mean_dg_broadcast(&self) -> f64 {
-        let n = self.rows.len().max(1) as f64;
-        self.rows.iter().map(|r| r.d_g_broadcast).sum::<f64>() / n
+        match &self.decay {
+            Some(d) => d.mean_var(0).0,
+            None => self.window_sums().mean(0, self.rows.len().max(1) as f64),
+        }
     }
 }
 
@@ -107,4 +471,12 @@ impl crate::policy::MemoryStats for MemoryFeatures {
     fn mem_mean_dg_broadcast(&self) -> f64 {
         self.mean_d_g_broadcast
     }
+
+    fn mem_stats_version(&self) -> u32 {
+        self.version
+    }
+
+    fn mem_features(&self) -> Option<&MemoryFeatures> {
+        Some(self)
+    }
 }
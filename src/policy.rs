@@ -1,15 +1,33 @@
+use anyhow::Result;
 use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
 
-use crate::adapter::normalize;
-use crate::sensory::ActionParams;
+use crate::adapter::{bayes_update, normalize};
+use crate::kernel::{kl, KernelConfig};
+use crate::memory::MemoryFeatures;
+use crate::sensory::{sensory_multimodal, ActionParams};
 
 const EPS: f64 = 1e-9;
 
-// Minimal interface the policy needs from memory.
-// Stable policy <- memory boundary.
+// Bounds the heuristic clamps its actions to.
+const SNIFF_RANGE: (f64, f64) = (0.1, 3.0);
+const TOUCH_RANGE: (f64, f64) = (0.0, 3.0);
+
+// Interface the policy needs from memory.
+// Stable policy <- memory boundary: version 1 is the two rates; from version 2
+// `mem_features` exposes the full `MemoryFeatures` (spread, trend, last-k).
 pub trait MemoryStats {
     fn mem_ignite_rate(&self) -> f64;
     fn mem_mean_dg_broadcast(&self) -> f64;
+    fn mem_stats_version(&self) -> u32 {
+        1
+    }
+    fn mem_features(&self) -> Option<&MemoryFeatures> {
+        None
+    }
 }
 
 fn entropy(q: &Array1<f64>) -> f64 {
@@ -18,9 +36,9 @@ fn entropy(q: &Array1<f64>) -> f64 {
     -q.iter().map(|&x| x * x.ln()).sum::<f64>()
 }
 
-// Policy: choose action parameters from belief state + memory stats + task vector.
-// Signature must match callsites in sniff_loop.rs / sniff_run.rs / tests.
-pub fn choose_action<M: MemoryStats>(
+// Heuristic policy: action parameters from belief state + memory stats + task
+// vector. Wrapped by `HeuristicPolicy`.
+pub fn choose_action<M: MemoryStats + ?Sized>(
     q_state: &Array1<f64>,
     mem: &M,
     task: &Array1<f64>,
@@ -49,7 +67,543 @@ pub fn choose_action<M: MemoryStats>(
     let touch = base_touch + 0.75 * (1.0 - h_norm) + 0.25 * ignite + 0.10 * (1.0 - task_mean);
 
     ActionParams {
-        sniff_strength: sniff.max(0.1).min(3.0),
-        touch_pressure: touch.max(0.0).min(3.0),
+        sniff_strength: sniff.clamp(SNIFF_RANGE.0, SNIFF_RANGE.1),
+        touch_pressure: touch.clamp(TOUCH_RANGE.0, TOUCH_RANGE.1),
+    }
+}
+
+// Candidate grid and weights of the expected-free-energy policy.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EfeConfig {
+    pub sniff_grid: Vec<f64>,
+    pub touch_grid: Vec<f64>,
+    // Weight of the pragmatic (task) term relative to information gain.
+    pub pragmatic_weight: f64,
+    // Cost per unit of sniff^2 + touch^2. Information gain only grows with a
+    // stronger action, so without it the largest candidate always wins.
+    pub effort_cost: f64,
+}
+
+impl Default for EfeConfig {
+    fn default() -> Self {
+        Self {
+            sniff_grid: vec![0.25, 0.5, 1.0, 1.5, 2.0, 3.0],
+            touch_grid: vec![0.0, 0.25, 0.5, 1.0, 2.0],
+            pragmatic_weight: 1.0,
+            effort_cost: 0.05,
+        }
+    }
+}
+
+impl EfeConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (name, grid) in [
+            ("sniff_grid", &self.sniff_grid),
+            ("touch_grid", &self.touch_grid),
+        ] {
+            if grid.is_empty() {
+                anyhow::bail!("efe.{} is empty", name);
+            }
+            if let Some(v) = grid.iter().find(|v| !v.is_finite() || **v < 0.0) {
+                anyhow::bail!("efe.{} has invalid value {}", name, v);
+            }
+        }
+        for (name, v) in [
+            ("pragmatic_weight", self.pragmatic_weight),
+            ("effort_cost", self.effort_cost),
+        ] {
+            if !v.is_finite() || v < 0.0 {
+                anyhow::bail!("efe.{} must be finite and >= 0, got {}", name, v);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Expected free energy of one candidate: efe = effort - epistemic
+// - pragmatic_weight * pragmatic. Lower is better.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionScore {
+    pub sniff_strength: f64,
+    pub touch_pressure: f64,
+    pub epistemic: f64,
+    pub pragmatic: f64,
+    pub effort: f64,
+    pub efe: f64,
+}
+
+// How the kernel's next update will treat an observation, for policies that
+// predict its outcome. Built only from what is known before the observation.
+pub struct Forecast<'a> {
+    // Prior the update combines with the likelihood (`Kernel::prior_for_step`
+    // after the predict step), which may depend on the action.
+    pub prior: &'a dyn Fn(&ActionParams) -> Array1<f64>,
+    // Olfactory columns p(o | s) of the observations that may arrive: the
+    // whole A tensor when loaded, else the previous event's column.
+    pub columns: &'a [Vec<f64>],
+    // Tactile column fused into each of them (the previous event's).
+    pub tactile: Option<&'a [f64]>,
+    // Configured olfactory weight; None derives it from the action.
+    pub w_olf: Option<f64>,
+}
+
+// Scores every (sniff, touch) pair of the grid. Each column is weighted by its
+// predictive probability sum_s prior(s) * p(o | s), and the posterior after
+// `o` is computed as the kernel's update does: the column fused with the
+// tactile column, tempered by the action, and combined with the prior.
+// Epistemic value is the expected KL(posterior || prior), pragmatic value the
+// expected task mass sum_s posterior(s) * task(s).
+pub fn efe_scores(
+    forecast: &Forecast<'_>,
+    task: &Array1<f64>,
+    cfg: &EfeConfig,
+) -> Vec<ActionScore> {
+    let mut scores = Vec::with_capacity(cfg.sniff_grid.len() * cfg.touch_grid.len());
+    for &sniff in &cfg.sniff_grid {
+        for &touch in &cfg.touch_grid {
+            let action = ActionParams {
+                sniff_strength: sniff,
+                touch_pressure: touch,
+            };
+            let prior = normalize(&(forecast.prior)(&action));
+            let cols: Vec<&Vec<f64>> = forecast
+                .columns
+                .iter()
+                .filter(|c| c.len() == prior.len())
+                .collect();
+            let pred: Vec<f64> = cols
+                .iter()
+                .map(|c| {
+                    prior
+                        .iter()
+                        .zip(c.iter())
+                        .map(|(p, l)| p * l)
+                        .sum::<f64>()
+                        .max(0.0)
+                })
+                .collect();
+            let z = pred.iter().sum::<f64>().max(EPS);
+            let l = prior.len().min(task.len());
+
+            let (mut epistemic, mut pragmatic) = (0.0, 0.0);
+            for (c, p) in cols.iter().zip(&pred) {
+                let w = p / z;
+                let sensory = sensory_multimodal(
+                    c.to_vec(),
+                    forecast.tactile.map(<[f64]>::to_vec),
+                    forecast.w_olf,
+                    sniff,
+                    touch,
+                );
+                let lik = normalize(&Array1::from(sensory.lik_mod));
+                let post = bayes_update(&prior, &lik);
+                epistemic += w * kl(&post, &prior);
+                pragmatic += w * (0..l).map(|s| post[s] * task[s]).sum::<f64>();
+            }
+            let effort = cfg.effort_cost * (sniff * sniff + touch * touch);
+            scores.push(ActionScore {
+                sniff_strength: sniff,
+                touch_pressure: touch,
+                epistemic,
+                pragmatic,
+                effort,
+                efe: effort - epistemic - cfg.pragmatic_weight * pragmatic,
+            });
+        }
+    }
+    scores
+}
+
+// Active-inference policy: the grid candidate with the lowest expected free
+// energy (first on ties), plus every candidate's score.
+pub fn choose_action_efe(
+    forecast: &Forecast<'_>,
+    task: &Array1<f64>,
+    cfg: &EfeConfig,
+) -> (ActionParams, Vec<ActionScore>) {
+    let scores = efe_scores(forecast, task, cfg);
+    let best = scores
+        .iter()
+        .reduce(|a, b| if b.efe < a.efe { b } else { a })
+        .expect("EfeConfig::validate rejects empty grids");
+    let action = ActionParams {
+        sniff_strength: best.sniff_strength,
+        touch_pressure: best.touch_pressure,
+    };
+    (action, scores)
+}
+
+// What a policy sees when it picks the action for step `t`.
+pub struct PolicyInput<'a> {
+    pub q_state: &'a Array1<f64>,
+    pub mem: &'a dyn MemoryStats,
+    pub task: &'a Array1<f64>,
+    pub t: u64,
+    // What the kernel's update will do with the coming observation; None when
+    // the policy runs outside a kernel.
+    pub forecast: Option<Forecast<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolicyOutput {
+    pub action: ActionParams,
+    // Recorded in the replay row as `policy_diagnostics`.
+    pub diagnostics: Option<Value>,
+}
+
+impl PolicyOutput {
+    fn plain(action: ActionParams) -> Self {
+        Self {
+            action,
+            diagnostics: None,
+        }
+    }
+}
+
+// Picks sniff/touch for events that do not carry them. Policies may keep state
+// across steps; `state`/`restore` carry it through checkpoints.
+pub trait Policy: fmt::Debug + Send {
+    fn name(&self) -> &str;
+    fn choose(&mut self, input: &PolicyInput<'_>) -> PolicyOutput;
+    fn state(&self) -> Option<Value> {
+        None
+    }
+    fn restore(&mut self, _state: &Value) -> Result<()> {
+        Ok(())
+    }
+    fn clone_box(&self) -> Box<dyn Policy>;
+}
+
+impl Clone for Box<dyn Policy> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HeuristicPolicy;
+
+impl Policy for HeuristicPolicy {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn choose(&mut self, input: &PolicyInput<'_>) -> PolicyOutput {
+        PolicyOutput::plain(choose_action(input.q_state, input.mem, input.task))
+    }
+
+    fn clone_box(&self) -> Box<dyn Policy> {
+        Box::new(self.clone())
+    }
+}
+
+// Fixed schedule: `actions[t % len]`, whatever the belief.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixedConfig {
+    pub actions: Vec<ActionParams>,
+}
+
+impl Default for FixedConfig {
+    fn default() -> Self {
+        Self {
+            actions: vec![ActionParams {
+                sniff_strength: 1.0,
+                touch_pressure: 0.25,
+            }],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FixedPolicy {
+    pub cfg: FixedConfig,
+}
+
+impl Policy for FixedPolicy {
+    fn name(&self) -> &str {
+        "fixed"
+    }
+
+    fn choose(&mut self, input: &PolicyInput<'_>) -> PolicyOutput {
+        let i = (input.t % self.cfg.actions.len() as u64) as usize;
+        PolicyOutput::plain(self.cfg.actions[i].clone())
+    }
+
+    fn clone_box(&self) -> Box<dyn Policy> {
+        Box::new(self.clone())
+    }
+}
+
+// Uniform random actions from a seeded generator; a baseline for A/B runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RandomConfig {
+    pub seed: u64,
+    pub sniff_range: [f64; 2],
+    pub touch_range: [f64; 2],
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            sniff_range: [0.1, 3.0],
+            touch_range: [0.0, 3.0],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RandomPolicy {
+    pub cfg: RandomConfig,
+    rng: u64,
+}
+
+impl RandomPolicy {
+    pub fn new(cfg: RandomConfig) -> Self {
+        let rng = cfg.seed;
+        Self { cfg, rng }
+    }
+
+    // splitmix64, mapped to [0, 1).
+    fn next_unit(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Policy for RandomPolicy {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn choose(&mut self, _input: &PolicyInput<'_>) -> PolicyOutput {
+        let [s0, s1] = self.cfg.sniff_range;
+        let [t0, t1] = self.cfg.touch_range;
+        let sniff = s0 + (s1 - s0) * self.next_unit();
+        let touch = t0 + (t1 - t0) * self.next_unit();
+        PolicyOutput::plain(ActionParams {
+            sniff_strength: sniff,
+            touch_pressure: touch,
+        })
+    }
+
+    fn state(&self) -> Option<Value> {
+        Some(Value::from(self.rng))
+    }
+
+    fn restore(&mut self, state: &Value) -> Result<()> {
+        self.rng = state
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("random policy state must be a u64, got {}", state))?;
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn Policy> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EfePolicy {
+    pub cfg: EfeConfig,
+}
+
+impl Policy for EfePolicy {
+    fn name(&self) -> &str {
+        "efe"
+    }
+
+    // Diagnostics: {"scores": [ActionScore, ...]} over the whole grid.
+    // Without a forecast every candidate has zero expected value, so only the
+    // effort counts.
+    fn choose(&mut self, input: &PolicyInput<'_>) -> PolicyOutput {
+        let prior = |_: &ActionParams| input.q_state.clone();
+        let fallback = Forecast {
+            prior: &prior,
+            columns: &[],
+            tactile: None,
+            w_olf: None,
+        };
+        let forecast = input.forecast.as_ref().unwrap_or(&fallback);
+        let (action, scores) = choose_action_efe(forecast, input.task, &self.cfg);
+        PolicyOutput {
+            action,
+            diagnostics: Some(serde_json::json!({ "scores": scores })),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Policy> {
+        Box::new(self.clone())
+    }
+}
+
+// Anti-oscillation wrapper around the heuristic: moves toward its target at
+// most `max_step` per step, by only `damping` of the way once the last-k
+// actions keep reversing, and stays `margin` inside the clamp range so the
+// actions do not sit on a bound.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmoothConfig {
+    pub max_step: f64,
+    pub damping: f64,
+    pub max_reversals: usize,
+    pub margin: f64,
+}
+
+impl Default for SmoothConfig {
+    fn default() -> Self {
+        Self {
+            max_step: 0.5,
+            damping: 0.5,
+            max_reversals: 2,
+            margin: 0.1,
+        }
+    }
+}
+
+impl SmoothConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(self.max_step.is_finite() && self.max_step > 0.0) {
+            anyhow::bail!("smooth.max_step must be > 0, got {}", self.max_step);
+        }
+        if !(0.0..=1.0).contains(&self.damping) {
+            anyhow::bail!("smooth.damping must be in [0,1], got {}", self.damping);
+        }
+        if !(0.0..=1.0).contains(&self.margin) {
+            anyhow::bail!("smooth.margin must be in [0,1], got {}", self.margin);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SmoothPolicy {
+    pub cfg: SmoothConfig,
+}
+
+impl SmoothPolicy {
+    // One action dimension: target from the heuristic, `last` from memory.
+    fn settle(&self, target: f64, last: f64, reversals: usize, (lo, hi): (f64, f64)) -> f64 {
+        let mut step = target - last;
+        if reversals >= self.cfg.max_reversals {
+            step *= self.cfg.damping;
+        }
+        let x = last + step.clamp(-self.cfg.max_step, self.cfg.max_step);
+        x.clamp(lo + self.cfg.margin, hi - self.cfg.margin)
+    }
+}
+
+impl Policy for SmoothPolicy {
+    fn name(&self) -> &str {
+        "smooth"
+    }
+
+    // Without version-2 memory features (or before the first step) it is the
+    // heuristic. Diagnostics: the heuristic target and whether damping applied.
+    fn choose(&mut self, input: &PolicyInput<'_>) -> PolicyOutput {
+        let target = choose_action(input.q_state, input.mem, input.task);
+        let recent = match input.mem.mem_features() {
+            Some(f) if input.mem.mem_stats_version() >= 2 => &f.recent,
+            _ => return PolicyOutput::plain(target),
+        };
+        let (Some(last_s), Some(last_t)) = (recent.last_sniff_strength, recent.last_touch_pressure)
+        else {
+            return PolicyOutput::plain(target);
+        };
+        let action = ActionParams {
+            sniff_strength: self.settle(
+                target.sniff_strength,
+                last_s,
+                recent.sniff_reversals,
+                SNIFF_RANGE,
+            ),
+            touch_pressure: self.settle(
+                target.touch_pressure,
+                last_t,
+                recent.touch_reversals,
+                TOUCH_RANGE,
+            ),
+        };
+        PolicyOutput {
+            action,
+            diagnostics: Some(serde_json::json!({
+                "target": target,
+                "damped_sniff": recent.sniff_reversals >= self.cfg.max_reversals,
+                "damped_touch": recent.touch_reversals >= self.cfg.max_reversals,
+            })),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Policy> {
+        Box::new(self.clone())
+    }
+}
+
+pub type PolicyFactory = fn(&KernelConfig) -> Result<Box<dyn Policy>>;
+
+// Policies by name, as selected by the config's `policy` key.
+#[derive(Clone, Default)]
+pub struct PolicyRegistry {
+    factories: BTreeMap<String, PolicyFactory>,
+}
+
+impl PolicyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // heuristic, smooth, fixed, random and efe.
+    pub fn builtin() -> Self {
+        let mut r = Self::new();
+        r.register("heuristic", |_| Ok(Box::new(HeuristicPolicy)));
+        r.register("smooth", |cfg| {
+            Ok(Box::new(SmoothPolicy {
+                cfg: cfg.smooth.clone(),
+            }))
+        });
+        r.register("fixed", |cfg| {
+            Ok(Box::new(FixedPolicy {
+                cfg: cfg.fixed.clone(),
+            }))
+        });
+        r.register("random", |cfg| {
+            Ok(Box::new(RandomPolicy::new(cfg.random.clone())))
+        });
+        r.register("efe", |cfg| {
+            Ok(Box::new(EfePolicy {
+                cfg: cfg.efe.clone(),
+            }))
+        });
+        r
+    }
+
+    pub fn register(&mut self, name: &str, factory: PolicyFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    pub fn build(&self, name: &str, cfg: &KernelConfig) -> Result<Box<dyn Policy>> {
+        match self.factories.get(name) {
+            Some(f) => f(cfg),
+            None => anyhow::bail!(
+                "unknown policy {:?} (known: {})",
+                name,
+                self.names().join(", ")
+            ),
+        }
     }
 }
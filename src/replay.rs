@@ -0,0 +1,184 @@
+use anyhow::{Context, Result};
+use ndarray::Array1;
+use std::fmt;
+
+use crate::kernel::{evaluate, KernelConfig};
+use crate::ledger::ReplayRow;
+use crate::sensory::modulate_likelihood;
+
+// One field of a replay row that the current kernel does not reproduce.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub t: u64,
+    pub field: &'static str,
+    pub recorded: String,
+    pub replayed: String,
+    pub max_abs_diff: f64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "t={} {}: recorded {} replayed {} (max |diff| {:.3e})",
+            self.t, self.field, self.recorded, self.replayed, self.max_abs_diff
+        )
+    }
+}
+
+fn max_abs_diff(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return f64::INFINITY;
+    }
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max)
+}
+
+struct Checker<'a> {
+    t: u64,
+    tol: f64,
+    out: &'a mut Vec<Divergence>,
+}
+
+impl Checker<'_> {
+    fn vec(&mut self, field: &'static str, recorded: &[f64], replayed: &[f64]) {
+        let d = max_abs_diff(recorded, replayed);
+        if d > self.tol || d.is_nan() {
+            self.out.push(Divergence {
+                t: self.t,
+                field,
+                recorded: format!("{:?}", recorded),
+                replayed: format!("{:?}", replayed),
+                max_abs_diff: d,
+            });
+        }
+    }
+
+    fn scalar(&mut self, field: &'static str, recorded: f64, replayed: f64) {
+        self.vec(field, &[recorded], &[replayed]);
+    }
+
+    fn label(&mut self, field: &'static str, recorded: &str, replayed: &str) {
+        if recorded != replayed {
+            self.out.push(Divergence {
+                t: self.t,
+                field,
+                recorded: recorded.to_string(),
+                replayed: replayed.to_string(),
+                max_abs_diff: f64::INFINITY,
+            });
+        }
+    }
+}
+
+// Re-run the ignition cycle from the inputs recorded in `row` (q_before, prior,
+// fused likelihood, action, task) and compare every derived output.
+pub fn verify_row(cfg: &KernelConfig, row: &ReplayRow, tol: f64) -> Result<Vec<Divergence>> {
+    if row.prior.is_empty() || row.lik_fused.is_empty() || row.task_vec.is_empty() {
+        anyhow::bail!(
+            "t={}: row lacks prior/lik_fused/task_vec (ledgers before schema v5 cannot be replayed)",
+            row.t
+        );
+    }
+
+    // Inputs of different sizes cannot be re-run; report them as divergent.
+    let n = row.q_before.len();
+    let mut out = Vec::new();
+    for (field, v) in [
+        ("prior", &row.prior),
+        ("lik_fused", &row.lik_fused),
+        ("task_vec", &row.task_vec),
+    ] {
+        if v.len() != n {
+            out.push(Divergence {
+                t: row.t,
+                field,
+                recorded: format!("length {}", v.len()),
+                replayed: format!("length {} (q_before)", n),
+                max_abs_diff: f64::INFINITY,
+            });
+        }
+    }
+    if !out.is_empty() {
+        return Ok(out);
+    }
+    cfg.validate_for_belief(n)
+        .with_context(|| format!("t={}", row.t))?;
+
+    let lik_fused = Array1::from(row.lik_fused.clone());
+    let (lik_mod, temperature) =
+        modulate_likelihood(&lik_fused, row.sniff_strength, row.touch_pressure);
+
+    let cycle = evaluate(
+        cfg,
+        &Array1::from(row.q_before.clone()),
+        &Array1::from(row.prior.clone()),
+        &lik_mod,
+        &Array1::from(row.task_vec.clone()),
+    );
+
+    let mut c = Checker {
+        t: row.t,
+        tol,
+        out: &mut out,
+    };
+    c.scalar("temperature", row.temperature, temperature);
+    c.vec("q_after", &row.q_after, cycle.q_after.as_slice().unwrap());
+    c.vec(
+        "q_broadcast",
+        &row.q_broadcast,
+        cycle.q_broadcast.as_slice().unwrap(),
+    );
+    c.vec("q_next", &row.q_next, cycle.q_next.as_slice().unwrap());
+    c.vec(
+        "broadcast",
+        &row.broadcast,
+        cycle.broadcast.as_slice().unwrap(),
+    );
+    c.scalar("g_before", row.g_before, cycle.g_before);
+    c.scalar("g_after_local", row.g_after_local, cycle.g_after_local);
+    c.scalar(
+        "g_after_broadcast",
+        row.g_after_broadcast,
+        cycle.g_after_broadcast,
+    );
+    c.scalar("d_g_local", row.d_g_local, cycle.d_g_local);
+    c.scalar("d_g_broadcast", row.d_g_broadcast, cycle.d_g_broadcast);
+    c.label("ignite_reason", &row.ignite_reason, &cycle.ignite_reason);
+    c.label(
+        "survivor_levels",
+        &format!("{:?}", row.survivor_levels),
+        &format!("{:?}", cycle.survivor_levels()),
+    );
+    Ok(out)
+}
+
+// Consecutive rows must chain: the next q_before is q_next, propagated by the
+// transition matrix when one was applied.
+pub fn verify_link(
+    cfg: &KernelConfig,
+    prev: &ReplayRow,
+    row: &ReplayRow,
+    tol: f64,
+) -> Option<Divergence> {
+    let q_next = Array1::from(prev.q_next.clone());
+    let expected = match (&cfg.transition, row.transition_idx) {
+        (Some(tr), Some(a)) if a < tr.matrices.len() && q_next.len() == tr.n_states() => {
+            tr.predict(a, &q_next).to_vec()
+        }
+        _ => prev.q_next.clone(),
+    };
+    let d = max_abs_diff(&expected, &row.q_before);
+    if d <= tol {
+        return None;
+    }
+    Some(Divergence {
+        t: row.t,
+        field: "q_before",
+        recorded: format!("{:?}", row.q_before),
+        replayed: format!("{:?}", expected),
+        max_abs_diff: d,
+    })
+}
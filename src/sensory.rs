@@ -2,8 +2,9 @@ use ndarray::Array1;
 use serde::{Deserialize, Serialize};
 
 const EPS: f64 = 1e-9;
+const K_TOUCH: f64 = 0.75;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ActionParams {
     pub sniff_strength: f64,
     pub touch_pressure: f64,
@@ -12,6 +13,9 @@ pub struct ActionParams {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SensoryOut {
     pub lik_raw: Vec<f64>,
+    pub lik_tact: Option<Vec<f64>>,
+    pub w_olf: Option<f64>,
+    pub lik_fused: Vec<f64>,
     pub lik_mod: Vec<f64>,
     pub temperature: f64,
 }
@@ -22,9 +26,8 @@ pub fn modulate_likelihood(
     touch_pressure: f64,
 ) -> (Array1<f64>, f64) {
     let t0 = 1.0f64;
-    let k_touch = 0.75f64;
 
-    let denom = (sniff_strength.max(0.0) + k_touch * touch_pressure.max(0.0)).max(EPS);
+    let denom = (sniff_strength.max(0.0) + K_TOUCH * touch_pressure.max(0.0)).max(EPS);
     let mut temp = t0 / denom;
 
     let t_min = 0.25f64;
@@ -51,7 +54,62 @@ pub fn sensory_from_flat_col(
     let lik_raw = Array1::from(a_flat_col.clone());
     let (lik_mod, temperature) = modulate_likelihood(&lik_raw, sniff_strength, touch_pressure);
     SensoryOut {
-        lik_raw: a_flat_col,
+        lik_raw: a_flat_col.clone(),
+        lik_tact: None,
+        w_olf: None,
+        lik_fused: a_flat_col,
+        lik_mod: lik_mod.to_vec(),
+        temperature,
+    }
+}
+
+// Discrete action regime: 0 when sniffing dominates, 1 when touch dominates.
+pub fn action_regime(sniff_strength: f64, touch_pressure: f64) -> usize {
+    if sniff_strength.max(0.0) >= K_TOUCH * touch_pressure.max(0.0) {
+        0
+    } else {
+        1
+    }
+}
+
+// Olfactory share implied by the action: the same sniff/touch mix that sets
+// the temperature in modulate_likelihood. Even split when both are zero.
+pub fn olfactory_weight_from_action(sniff_strength: f64, touch_pressure: f64) -> f64 {
+    let s = sniff_strength.max(0.0);
+    let t = K_TOUCH * touch_pressure.max(0.0);
+    if s + t <= EPS {
+        return 0.5;
+    }
+    s / (s + t)
+}
+
+// Olfactory + optional tactile column: fuse in log space, then temperature-modulate.
+// `w_olf = None` derives the weight from the action. Without tactile data this
+// is exactly sensory_from_flat_col.
+pub fn sensory_multimodal(
+    olf_flat_col: Vec<f64>,
+    tactile_flat_col: Option<Vec<f64>>,
+    w_olf: Option<f64>,
+    sniff_strength: f64,
+    touch_pressure: f64,
+) -> SensoryOut {
+    let tact = match tactile_flat_col {
+        None => return sensory_from_flat_col(olf_flat_col, sniff_strength, touch_pressure),
+        Some(t) => t,
+    };
+
+    let w = w_olf
+        .unwrap_or_else(|| olfactory_weight_from_action(sniff_strength, touch_pressure))
+        .clamp(0.0, 1.0);
+    let olf = Array1::from(olf_flat_col.clone());
+    let tact_arr = Array1::from(tact.clone());
+    let fused = fuse_likelihoods_logspace(&olf, Some(&tact_arr), w);
+    let (lik_mod, temperature) = modulate_likelihood(&fused, sniff_strength, touch_pressure);
+    SensoryOut {
+        lik_raw: olf_flat_col,
+        lik_tact: Some(tact),
+        w_olf: Some(w),
+        lik_fused: fused.to_vec(),
         lik_mod: lik_mod.to_vec(),
         temperature,
     }
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::kernel::StepOutput;
+use crate::ledger::ndjson_write_row;
+use crate::sensory::ActionParams;
+
+// Where events come from or rows go: `-` (stdin / stdout), `unix:PATH` (a
+// Unix domain socket, connected to as a client) or a plain file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Stdio,
+    Unix(PathBuf),
+    File(PathBuf),
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("empty endpoint".to_string()),
+            "-" => Ok(Endpoint::Stdio),
+            _ => Ok(match s.strip_prefix("unix:") {
+                Some(p) => Endpoint::Unix(PathBuf::from(p)),
+                None => Endpoint::File(PathBuf::from(s)),
+            }),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn connect(path: &std::path::Path) -> Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(path)
+        .with_context(|| format!("connecting to socket {}", path.display()))
+}
+
+impl Endpoint {
+    pub fn is_stdio(&self) -> bool {
+        *self == Endpoint::Stdio
+    }
+
+    pub fn open_input(&self) -> Result<Box<dyn BufRead>> {
+        Ok(match self {
+            Endpoint::Stdio => Box::new(BufReader::new(std::io::stdin())),
+            Endpoint::File(p) => Box::new(BufReader::new(
+                File::open(p).with_context(|| format!("opening {}", p.display()))?,
+            )),
+            #[cfg(unix)]
+            Endpoint::Unix(p) => Box::new(BufReader::new(connect(p)?)),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => anyhow::bail!("unix sockets are not supported on this platform"),
+        })
+    }
+
+    // Buffered; callers flush after each line they want delivered.
+    pub fn open_output(&self) -> Result<Box<dyn Write>> {
+        Ok(match self {
+            Endpoint::Stdio => Box::new(BufWriter::new(std::io::stdout())),
+            Endpoint::File(p) => Box::new(BufWriter::new(
+                File::create(p).with_context(|| format!("creating {}", p.display()))?,
+            )),
+            #[cfg(unix)]
+            Endpoint::Unix(p) => Box::new(BufWriter::new(connect(p)?)),
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => anyhow::bail!("unix sockets are not supported on this platform"),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmitKind {
+    Trace,
+    Replay,
+    Decision,
+}
+
+impl FromStr for EmitKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trace" => Ok(EmitKind::Trace),
+            "replay" => Ok(EmitKind::Replay),
+            "decision" => Ok(EmitKind::Decision),
+            other => Err(format!(
+                "unknown row kind {:?} (expected trace, replay or decision)",
+                other
+            )),
+        }
+    }
+}
+
+// What the kernel decided at one step, without the belief vectors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    pub t: u64,
+    pub ignited: bool,
+    pub ignite_reason: String,
+    pub sniff_strength: f64,
+    pub touch_pressure: f64,
+    pub action_source: String,
+}
+
+impl From<&StepOutput> for Decision {
+    fn from(out: &StepOutput) -> Self {
+        let r = &out.replay;
+        Decision {
+            t: r.t,
+            ignited: r.ignited,
+            ignite_reason: r.ignite_reason.clone(),
+            sniff_strength: r.sniff_strength,
+            touch_pressure: r.touch_pressure,
+            action_source: r.action_source.clone(),
+        }
+    }
+}
+
+// Closed-loop request to the controller: execute this action, then send the
+// next observation (expected at `t`; a later `t` still uses the action).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NextAction {
+    pub t: u64,
+    pub sniff_strength: f64,
+    pub touch_pressure: f64,
+}
+
+// A row tagged with its kind: {"kind":"trace","t":0,...}.
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    kind: &'static str,
+    #[serde(flatten)]
+    row: &'a T,
+}
+
+// Live output: each selected row of every step as one tagged NDJSON line,
+// flushed as soon as the step is done.
+pub struct RowStream {
+    out: Box<dyn Write>,
+    kinds: Vec<EmitKind>,
+}
+
+impl RowStream {
+    pub fn new(out: Box<dyn Write>, kinds: Vec<EmitKind>) -> Self {
+        Self { out, kinds }
+    }
+
+    pub fn emit(&mut self, step: &StepOutput) -> Result<()> {
+        for kind in &self.kinds {
+            match kind {
+                EmitKind::Trace => ndjson_write_row(
+                    &mut self.out,
+                    &Tagged {
+                        kind: "trace",
+                        row: &step.trace,
+                    },
+                )?,
+                EmitKind::Replay => ndjson_write_row(
+                    &mut self.out,
+                    &Tagged {
+                        kind: "replay",
+                        row: &step.replay,
+                    },
+                )?,
+                EmitKind::Decision => ndjson_write_row(
+                    &mut self.out,
+                    &Tagged {
+                        kind: "decision",
+                        row: &Decision::from(step),
+                    },
+                )?,
+            }
+        }
+        self.out.flush().context("flushing live output")
+    }
+
+    pub fn emit_action(&mut self, t: u64, action: &ActionParams) -> Result<()> {
+        let next = NextAction {
+            t,
+            sniff_strength: action.sniff_strength,
+            touch_pressure: action.touch_pressure,
+        };
+        ndjson_write_row(
+            &mut self.out,
+            &Tagged {
+                kind: "action",
+                row: &next,
+            },
+        )?;
+        self.out.flush().context("flushing live output")
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::kernel::IGNITE_REASONS;
+use crate::ledger::{ReplayRow, TraceRow};
+
+const COHERENCE_BINS: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub n: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub p10: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+impl Stats {
+    // None for an empty sample. Percentiles interpolate linearly between ranks.
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut v = values.to_vec();
+        v.sort_by(f64::total_cmp);
+        Some(Self {
+            n: v.len(),
+            mean: v.iter().sum::<f64>() / v.len() as f64,
+            min: v[0],
+            p10: percentile(&v, 0.10),
+            p50: percentile(&v, 0.50),
+            p90: percentile(&v, 0.90),
+            max: v[v.len() - 1],
+        })
+    }
+}
+
+// `sorted` must be non-empty and ascending.
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+// Equal-width bins over [lo, hi]; values outside go to the end bins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(values: &[f64], lo: f64, hi: f64, bins: usize) -> Self {
+        let width = (hi - lo) / bins as f64;
+        let mut counts = vec![0; bins];
+        for &x in values {
+            let b = ((x - lo) / width).floor();
+            counts[(b.max(0.0) as usize).min(bins - 1)] += 1;
+        }
+        Self {
+            edges: (0..=bins).map(|i| lo + width * i as f64).collect(),
+            counts,
+        }
+    }
+}
+
+// Per-run ignition statistics. Ignition counts and free-energy drops come from
+// the trace ledger when given (replay otherwise); coherence is only in trace
+// rows, temperature and action source only in replay rows.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub steps: usize,
+    pub ignited: usize,
+    pub ignition_rate: f64,
+    pub ignite_reasons: BTreeMap<String, usize>,
+    pub d_g_local: Option<Stats>,
+    pub d_g_broadcast: Option<Stats>,
+    pub coherence: Option<Stats>,
+    pub coherence_hist: Option<Histogram>,
+    pub temperature: Option<Stats>,
+    pub action_source: BTreeMap<String, usize>,
+}
+
+impl RunSummary {
+    pub fn new(trace: &[TraceRow], replay: &[ReplayRow]) -> Self {
+        let decisions: Vec<(&str, f64, f64)> = if !trace.is_empty() {
+            trace
+                .iter()
+                .map(|r| (r.ignite_reason.as_str(), r.d_g_local, r.d_g_broadcast))
+                .collect()
+        } else {
+            replay
+                .iter()
+                .map(|r| (r.ignite_reason.as_str(), r.d_g_local, r.d_g_broadcast))
+                .collect()
+        };
+
+        let mut s = RunSummary {
+            steps: decisions.len(),
+            ..Default::default()
+        };
+        for r in IGNITE_REASONS {
+            s.ignite_reasons.insert(r.to_string(), 0);
+        }
+        for (reason, _, _) in &decisions {
+            *s.ignite_reasons.entry(reason.to_string()).or_default() += 1;
+        }
+        s.ignited = s.ignite_reasons["ignite"];
+        s.ignition_rate = if s.steps > 0 {
+            s.ignited as f64 / s.steps as f64
+        } else {
+            0.0
+        };
+        let local: Vec<f64> = decisions.iter().map(|d| d.1).collect();
+        let broadcast: Vec<f64> = decisions.iter().map(|d| d.2).collect();
+        s.d_g_local = Stats::from_values(&local);
+        s.d_g_broadcast = Stats::from_values(&broadcast);
+
+        if !trace.is_empty() {
+            let coh: Vec<f64> = trace.iter().map(|r| r.coherence).collect();
+            s.coherence = Stats::from_values(&coh);
+            s.coherence_hist = Some(Histogram::new(&coh, 0.0, 1.0, COHERENCE_BINS));
+        }
+
+        let temps: Vec<f64> = replay.iter().map(|r| r.temperature).collect();
+        s.temperature = Stats::from_values(&temps);
+        for r in replay {
+            *s.action_source.entry(r.action_source.clone()).or_default() += 1;
+        }
+        s
+    }
+}
+
+fn stats_line(f: &mut fmt::Formatter<'_>, name: &str, s: &Option<Stats>) -> fmt::Result {
+    match s {
+        Some(s) => writeln!(
+            f,
+            "{:<15} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+            name, s.mean, s.min, s.p10, s.p50, s.p90, s.max
+        ),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "steps           {}", self.steps)?;
+        writeln!(
+            f,
+            "ignition rate   {:.3} ({}/{})",
+            self.ignition_rate, self.ignited, self.steps
+        )?;
+        writeln!(f, "ignite_reason")?;
+        for r in IGNITE_REASONS {
+            writeln!(
+                f,
+                "  {:<15} {}",
+                r,
+                self.ignite_reasons.get(r).unwrap_or(&0)
+            )?;
+        }
+        for (r, n) in &self.ignite_reasons {
+            if !IGNITE_REASONS.contains(&r.as_str()) {
+                writeln!(f, "  {:<15} {}", r, n)?;
+            }
+        }
+
+        writeln!(
+            f,
+            "{:<15} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "", "mean", "min", "p10", "p50", "p90", "max"
+        )?;
+        stats_line(f, "d_g_local", &self.d_g_local)?;
+        stats_line(f, "d_g_broadcast", &self.d_g_broadcast)?;
+        stats_line(f, "coherence", &self.coherence)?;
+        stats_line(f, "temperature", &self.temperature)?;
+
+        if let Some(h) = &self.coherence_hist {
+            writeln!(f, "coherence histogram")?;
+            for (i, n) in h.counts.iter().enumerate() {
+                let close = if i + 1 == h.counts.len() { ']' } else { ')' };
+                writeln!(
+                    f,
+                    "  [{:.1}, {:.1}{} {}",
+                    h.edges[i],
+                    h.edges[i + 1],
+                    close,
+                    n
+                )?;
+            }
+        }
+        if !self.action_source.is_empty() {
+            writeln!(f, "action_source")?;
+            for (src, n) in &self.action_source {
+                writeln!(f, "  {:<15} {}", src, n)?;
+            }
+        }
+        Ok(())
+    }
+}
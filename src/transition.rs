@@ -0,0 +1,121 @@
+use anyhow::Result;
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+
+use crate::adapter::normalize;
+use crate::sensory::action_regime;
+
+const COL_TOL: f64 = 1e-6;
+
+// How a step picks its transition matrix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionIndex {
+    // The event's discrete `action_id` (0 when absent).
+    #[default]
+    ActionId,
+    // 0 = sniff-dominant, 1 = touch-dominant action (see sensory::action_regime).
+    Regime,
+}
+
+// Hidden-state transition model: one B matrix per action, B[a][to][from].
+// Each column is a distribution over next states.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransitionConfig {
+    #[serde(default)]
+    pub index: TransitionIndex,
+    pub matrices: Vec<Vec<Vec<f64>>>,
+}
+
+impl TransitionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.matrices.is_empty() {
+            anyhow::bail!("transition.matrices is empty");
+        }
+        if self.index == TransitionIndex::Regime && self.matrices.len() < 2 {
+            anyhow::bail!(
+                "transition.index = regime needs 2 matrices, got {}",
+                self.matrices.len()
+            );
+        }
+        let n = self.matrices[0].len();
+        for (a, b) in self.matrices.iter().enumerate() {
+            if b.len() != n || b.iter().any(|row| row.len() != n) {
+                anyhow::bail!("transition matrix {} is not {}x{}", a, n, n);
+            }
+            for from in 0..n {
+                let mut col = 0.0;
+                for (to, row) in b.iter().enumerate() {
+                    let v = row[from];
+                    if !v.is_finite() || v < 0.0 {
+                        anyhow::bail!(
+                            "transition matrix {} has bad entry [{}][{}] = {}",
+                            a,
+                            to,
+                            from,
+                            v
+                        );
+                    }
+                    col += v;
+                }
+                if (col - 1.0).abs() > COL_TOL {
+                    anyhow::bail!(
+                        "transition matrix {} column {} sums to {}, expected 1",
+                        a,
+                        from,
+                        col
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn n_states(&self) -> usize {
+        self.matrices.first().map(|b| b.len()).unwrap_or(0)
+    }
+
+    pub fn select(
+        &self,
+        action_id: Option<usize>,
+        sniff_strength: f64,
+        touch_pressure: f64,
+    ) -> Result<usize> {
+        let a = match self.index {
+            TransitionIndex::ActionId => action_id.unwrap_or(0),
+            TransitionIndex::Regime => action_regime(sniff_strength, touch_pressure),
+        };
+        self.check_index(a)?;
+        Ok(a)
+    }
+
+    // Everything `select` can reject, checked before the action is known: the
+    // regime index is 0 or 1 whatever the action.
+    pub fn check_event(&self, action_id: Option<usize>) -> Result<()> {
+        match self.index {
+            TransitionIndex::ActionId => self.check_index(action_id.unwrap_or(0)),
+            TransitionIndex::Regime => self.check_index(1),
+        }
+    }
+
+    fn check_index(&self, a: usize) -> Result<()> {
+        if a >= self.matrices.len() {
+            anyhow::bail!(
+                "transition index {} out of range ({} matrices)",
+                a,
+                self.matrices.len()
+            );
+        }
+        Ok(())
+    }
+
+    // Predict step: q' = B[a] q.
+    pub fn predict(&self, a: usize, q: &Array1<f64>) -> Array1<f64> {
+        let b = &self.matrices[a];
+        let n = b.len();
+        let flat: Vec<f64> = b.iter().flatten().cloned().collect();
+        let m = Array2::from_shape_vec((n, n), flat).unwrap();
+        normalize(&m.dot(q))
+    }
+}
@@ -1,14 +1,82 @@
+use std::fmt;
+
 const EPS: f64 = 1e-9;
 
-pub fn ravel_multi_index(o: &[usize], shape: &[usize]) -> usize {
+// Why a multi-index could not be mapped to (or from) a flat index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexError {
+    RankMismatch {
+        expected: usize,
+        got: usize,
+    },
+    OutOfBounds {
+        axis: usize,
+        value: usize,
+        dim: usize,
+    },
+    FlatOutOfBounds {
+        idx: usize,
+        size: usize,
+    },
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::RankMismatch { expected, got } => {
+                write!(f, "observation has {} axes, expected {}", got, expected)
+            }
+            IndexError::OutOfBounds { axis, value, dim } => write!(
+                f,
+                "observation axis {} has value {}, must be < {}",
+                axis, value, dim
+            ),
+            IndexError::FlatOutOfBounds { idx, size } => {
+                write!(f, "flat index {} out of range for size {}", idx, size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+// Row-major flat index of `o` within `shape`.
+pub fn ravel_multi_index(o: &[usize], shape: &[usize]) -> Result<usize, IndexError> {
+    if o.len() != shape.len() {
+        return Err(IndexError::RankMismatch {
+            expected: shape.len(),
+            got: o.len(),
+        });
+    }
     let mut idx = 0usize;
     let mut stride = 1usize;
-    for (i, dim) in shape.iter().rev().enumerate() {
-        let oi = o[o.len() - 1 - i];
+    for (axis, (&oi, &dim)) in o.iter().zip(shape).enumerate().rev() {
+        if oi >= dim {
+            return Err(IndexError::OutOfBounds {
+                axis,
+                value: oi,
+                dim,
+            });
+        }
         idx += oi * stride;
-        stride *= *dim;
+        stride *= dim;
+    }
+    Ok(idx)
+}
+
+// Inverse of ravel_multi_index.
+pub fn unravel_index(idx: usize, shape: &[usize]) -> Result<Vec<usize>, IndexError> {
+    let size: usize = shape.iter().product();
+    if idx >= size {
+        return Err(IndexError::FlatOutOfBounds { idx, size });
+    }
+    let mut o = vec![0usize; shape.len()];
+    let mut rest = idx;
+    for (axis, &dim) in shape.iter().enumerate().rev() {
+        o[axis] = rest % dim;
+        rest /= dim;
     }
-    idx
+    Ok(o)
 }
 
 pub fn clamp01(x: f64) -> f64 {
@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::event::StreamEvent;
+
+// What to do with an event that fails validation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationMode {
+    // Abort the run on the first bad line.
+    #[default]
+    FailFast,
+    // Log the line and continue without it.
+    Skip,
+    // Fix what can be fixed (see `repair`), skip the rest.
+    Repair,
+}
+
+impl FromStr for ValidationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" | "fail_fast" | "fail-fast" => Ok(ValidationMode::FailFast),
+            "skip" => Ok(ValidationMode::Skip),
+            "repair" => Ok(ValidationMode::Repair),
+            other => Err(format!(
+                "unknown validation mode {:?} (expected fail, skip or repair)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub field: &'static str,
+    pub message: String,
+    pub repairable: bool,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+pub fn describe(issues: &[Issue]) -> String {
+    issues
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn check_values(field: &'static str, v: &[f64], issues: &mut Vec<Issue>) {
+    if let Some(i) = v.iter().position(|x| !x.is_finite()) {
+        issues.push(Issue {
+            field,
+            message: format!("non-finite value {} at index {}", v[i], i),
+            repairable: true,
+        });
+    }
+    if let Some(i) = v.iter().position(|&x| x < 0.0) {
+        issues.push(Issue {
+            field,
+            message: format!("negative value {} at index {}", v[i], i),
+            repairable: true,
+        });
+    }
+    if !v.is_empty() && !v.iter().any(|&x| x > 0.0) {
+        issues.push(Issue {
+            field,
+            message: "no positive mass".to_string(),
+            repairable: true,
+        });
+    }
+}
+
+fn check_len(field: &'static str, got: usize, n: usize, repairable: bool, issues: &mut Vec<Issue>) {
+    if got != n {
+        issues.push(Issue {
+            field,
+            message: format!("length {} does not match p_prior length {}", got, n),
+            repairable,
+        });
+    }
+}
+
+// Schema checks for one event. `prev_t` is the last accepted `t`; `has_tensor`
+// allows an empty A_flat_col (the kernel slices it from A).
+pub fn check_event(ev: &StreamEvent, prev_t: Option<u64>, has_tensor: bool) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let n = ev.p_prior.len();
+
+    if n == 0 {
+        issues.push(Issue {
+            field: "p_prior",
+            message: "empty".to_string(),
+            repairable: false,
+        });
+        return issues;
+    }
+    check_values("p_prior", &ev.p_prior, &mut issues);
+
+    match ev.A_shape.first() {
+        None if !has_tensor => issues.push(Issue {
+            field: "A_shape",
+            message: "missing and no likelihood tensor is loaded".to_string(),
+            repairable: false,
+        }),
+        Some(&d) if d != n => issues.push(Issue {
+            field: "A_shape",
+            message: format!("first dim {} does not match p_prior length {}", d, n),
+            repairable: false,
+        }),
+        _ => {}
+    }
+
+    if !(has_tensor && ev.a_flat_col.is_empty()) {
+        check_len("a_flat_col", ev.a_flat_col.len(), n, false, &mut issues);
+        check_values("a_flat_col", &ev.a_flat_col, &mut issues);
+    }
+    check_len("task_vec", ev.task_vec.len(), n, true, &mut issues);
+    if let Some(q0) = &ev.q0 {
+        check_len("q0", q0.len(), n, true, &mut issues);
+        check_values("q0", q0, &mut issues);
+    }
+    if let Some(tact) = &ev.tactile_flat_col {
+        check_len("tactile_flat_col", tact.len(), n, true, &mut issues);
+        check_values("tactile_flat_col", tact, &mut issues);
+    }
+    if let Some(p) = prev_t {
+        if ev.t <= p {
+            issues.push(Issue {
+                field: "t",
+                message: format!("{} does not increase on previous t {}", ev.t, p),
+                repairable: true,
+            });
+        }
+    }
+    issues
+}
+
+// Clamp NaN/negative entries to 0 and replace massless vectors by uniform.
+fn repair_values(v: &mut [f64]) {
+    for x in v.iter_mut() {
+        if !x.is_finite() || *x < 0.0 {
+            *x = 0.0;
+        }
+    }
+    if v.iter().all(|&x| x <= 0.0) {
+        let u = 1.0 / v.len().max(1) as f64;
+        v.iter_mut().for_each(|x| *x = u);
+    }
+}
+
+// Repairs: bad values clamped (uniform if nothing is left), task_vec padded or
+// truncated to the belief size, mismatched q0 / tactile columns dropped, and a
+// non-increasing `t` moved to prev_t + 1.
+pub fn repair(ev: &mut StreamEvent, prev_t: Option<u64>) {
+    let n = ev.p_prior.len();
+    repair_values(&mut ev.p_prior);
+    repair_values(&mut ev.a_flat_col);
+    ev.task_vec.resize(n, 0.0);
+    for x in ev.task_vec.iter_mut() {
+        if !x.is_finite() {
+            *x = 0.0;
+        }
+    }
+    if ev.q0.as_ref().is_some_and(|q| q.len() != n) {
+        ev.q0 = None;
+    }
+    if let Some(q0) = ev.q0.as_mut() {
+        repair_values(q0);
+    }
+    if ev.tactile_flat_col.as_ref().is_some_and(|c| c.len() != n) {
+        ev.tactile_flat_col = None;
+    }
+    if let Some(c) = ev.tactile_flat_col.as_mut() {
+        repair_values(c);
+    }
+    if let Some(p) = prev_t {
+        if ev.t <= p {
+            ev.t = p + 1;
+        }
+    }
+}
+
+pub enum Verdict {
+    Accept(StreamEvent),
+    Repaired(StreamEvent, Vec<Issue>),
+    Skipped(Vec<Issue>),
+}
+
+// Applies a ValidationMode to a stream, tracking the last accepted `t` and
+// the belief size every later event must keep.
+pub struct Validator {
+    pub mode: ValidationMode,
+    pub has_tensor: bool,
+    pub prev_t: Option<u64>,
+    pub n_states: Option<usize>,
+    pub n_repaired: usize,
+    pub n_skipped: usize,
+    // prev_t, n_states and whether the event was repaired, from before the
+    // last admitted event.
+    undo: Option<(Option<u64>, Option<usize>, bool)>,
+}
+
+impl Validator {
+    pub fn new(mode: ValidationMode, has_tensor: bool) -> Self {
+        Self {
+            mode,
+            has_tensor,
+            prev_t: None,
+            n_states: None,
+            n_repaired: 0,
+            n_skipped: 0,
+            undo: None,
+        }
+    }
+
+    // Err only in fail-fast mode.
+    pub fn admit(&mut self, line_no: usize, mut ev: StreamEvent) -> anyhow::Result<Verdict> {
+        let mut issues = check_event(&ev, self.prev_t, self.has_tensor);
+        let n = ev.p_prior.len();
+        if let Some(prev_n) = self.n_states.filter(|&m| n > 0 && m != n) {
+            issues.push(Issue {
+                field: "p_prior",
+                message: format!(
+                    "length {} changes the belief size {} of earlier events",
+                    n, prev_n
+                ),
+                repairable: false,
+            });
+        }
+        self.undo = None;
+        if issues.is_empty() {
+            self.undo = Some((self.prev_t, self.n_states, false));
+            self.prev_t = Some(ev.t);
+            self.n_states = Some(n);
+            return Ok(Verdict::Accept(ev));
+        }
+        match self.mode {
+            ValidationMode::FailFast => {
+                anyhow::bail!("line {}: {}", line_no, describe(&issues))
+            }
+            ValidationMode::Repair if issues.iter().all(|i| i.repairable) => {
+                self.undo = Some((self.prev_t, self.n_states, true));
+                repair(&mut ev, self.prev_t);
+                self.prev_t = Some(ev.t);
+                self.n_states = Some(n);
+                self.n_repaired += 1;
+                Ok(Verdict::Repaired(ev, issues))
+            }
+            _ => {
+                self.n_skipped += 1;
+                Ok(Verdict::Skipped(issues))
+            }
+        }
+    }
+
+    // Lines the reader itself rejected (bad JSON, observation out of range).
+    pub fn reject(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+        if self.mode == ValidationMode::FailFast {
+            return Err(err);
+        }
+        self.n_skipped += 1;
+        Ok(())
+    }
+
+    // The event just admitted was refused by the kernel (A_flat_col or
+    // A_shape against a loaded tensor, transition index out of range): handled
+    // like a reader error, and its `t` and belief size no longer count as seen.
+    pub fn reject_admitted(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+        if let Some((prev_t, n_states, repaired)) = self.undo.take() {
+            self.prev_t = prev_t;
+            self.n_states = n_states;
+            if repaired {
+                self.n_repaired -= 1;
+            }
+        }
+        self.reject(err)
+    }
+}
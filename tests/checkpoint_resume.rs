@@ -0,0 +1,151 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::fresh_dir;
+use llm_nature_experiential::checkpoint::Checkpoint;
+use llm_nature_experiential::config::RunConfig;
+use llm_nature_experiential::event::StreamEvent;
+use llm_nature_experiential::kernel::Kernel;
+use llm_nature_experiential::ledger::{partial_path, LEDGER_SCHEMA_VERSION};
+
+fn sniff_loop(input: &Path, out_dir: &Path, extra: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .arg("--input")
+        .arg(input)
+        .arg("--out-dir")
+        .arg(out_dir)
+        .args(["--hash-chain"])
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn interrupted_run_resumes_to_identical_ledgers() {
+    let stream = Path::new("data/sniff_stream_tactile.ndjson");
+    let full = fresh_dir("checkpoint_full");
+    assert!(sniff_loop(stream, &full, &[]).status.success());
+
+    // Same stream followed by a corrupt line: fail-fast aborts after 3 steps,
+    // one step past the last checkpoint.
+    let dir = fresh_dir("checkpoint_resume");
+    let broken = dir.join("broken.ndjson");
+    let text = std::fs::read_to_string(stream).unwrap();
+    std::fs::write(&broken, format!("{}{{not json\n", text)).unwrap();
+    let out = sniff_loop(
+        &broken,
+        &dir,
+        &["--checkpoint-every", "2", "--flush-every", "1"],
+    );
+    assert!(!out.status.success());
+
+    let trace = dir.join("trace_loop.ndjson");
+    let replay = dir.join("replay_loop.ndjson");
+    assert!(!trace.exists());
+    let partial = std::fs::read_to_string(partial_path(&trace)).unwrap();
+    assert_eq!(partial.lines().count(), 4);
+    let ckpt = Checkpoint::load(&dir.join("checkpoint.json")).unwrap();
+    assert_eq!((ckpt.steps, ckpt.last_t), (2, Some(1)));
+    assert_eq!(ckpt.mem_rows.len(), 2);
+
+    // A different config must not resume.
+    let out = sniff_loop(stream, &dir, &["--resume", "--set", "params.delta=1.0"]);
+    assert!(!out.status.success());
+
+    // Nor may a ledger written under another schema.
+    let older = partial.replacen(
+        &format!("\"schema_version\":{}", LEDGER_SCHEMA_VERSION),
+        "\"schema_version\":2",
+        1,
+    );
+    assert_ne!(older, partial);
+    std::fs::write(partial_path(&trace), &older).unwrap();
+    let out = sniff_loop(stream, &dir, &["--resume"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("has schema v2"), "{}", stderr);
+    std::fs::write(partial_path(&trace), &partial).unwrap();
+
+    let out = sniff_loop(stream, &dir, &["--resume"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    for (a, b) in [
+        (&trace, full.join("trace_loop.ndjson")),
+        (&replay, full.join("replay_loop.ndjson")),
+    ] {
+        assert_eq!(
+            std::fs::read_to_string(a).unwrap(),
+            std::fs::read_to_string(b).unwrap()
+        );
+    }
+    assert!(!partial_path(&trace).exists());
+
+    let verify = Command::new(env!("CARGO_BIN_EXE_ledger_verify"))
+        .arg(&trace)
+        .arg(&replay)
+        .output()
+        .unwrap();
+    assert!(verify.status.success());
+    assert_eq!(
+        Checkpoint::load(&dir.join("checkpoint.json"))
+            .unwrap()
+            .steps,
+        3
+    );
+}
+
+fn drifting_event(t: u64) -> StreamEvent {
+    let x = t as f64;
+    let mut col = vec![
+        0.2 + 0.15 * (0.7 * x).sin(),
+        0.5 + 0.3 * (0.3 * x).cos(),
+        0.1 + 0.05 * (1.1 * x).sin().abs(),
+        0.1 + 0.08 * (0.5 * x).cos().abs(),
+    ];
+    let z: f64 = col.iter().sum();
+    col.iter_mut().for_each(|v| *v /= z);
+    serde_json::from_value(serde_json::json!({
+        "t": t,
+        "o": [1, 2],
+        "A_shape": [4, 3, 5],
+        "A_flat_col": col,
+        "p_prior": [0.4, 0.2, 0.2, 0.2],
+        "task_vec": [0.0, 1.0, 0.0, 0.0],
+        "sniff_strength": 0.3 + (0.9 * x).sin().abs(),
+        "touch_pressure": 0.5 * (0.4 * x).cos().abs()
+    }))
+    .unwrap()
+}
+
+#[test]
+fn restored_memory_matches_the_live_kernel() {
+    let mut cfg = RunConfig::default();
+    cfg.kernel.mem_window = 5;
+    let mut live = Kernel::new(cfg.kernel.clone()).unwrap();
+    for t in 0..23 {
+        live.step(&drifting_event(t)).unwrap();
+    }
+
+    let saved = serde_json::to_string(&Checkpoint::capture(&cfg, &live, Some(22), 23).unwrap());
+    let checkpoint: Checkpoint = serde_json::from_str(&saved.unwrap()).unwrap();
+    let mut resumed = Kernel::new(cfg.kernel.clone()).unwrap();
+    checkpoint.restore(&mut resumed).unwrap();
+    assert_eq!(resumed.mem.features(22), live.mem.features(22));
+
+    // Several more evictions on both sides.
+    for t in 23..80 {
+        let ev = drifting_event(t);
+        let a = live.step(&ev).unwrap();
+        let b = resumed.step(&ev).unwrap();
+        assert_eq!(resumed.mem.features(t), live.mem.features(t), "t {}", t);
+        assert_eq!(
+            serde_json::to_value(&a.replay).unwrap(),
+            serde_json::to_value(&b.replay).unwrap()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use common::fresh_dir;
+use serde_json::Value;
+
+// Drives sniff_loop as the controller would, sending the stream's events
+// with the given `t` values (cycling through the file's lines).
+fn run_closed_loop(name: &str, ts: &[u64]) {
+    let dir = fresh_dir(name);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .args(["--input", "-", "--stream-out", "-", "--emit", "decision"])
+        .arg("--closed-loop")
+        .arg("--out-dir")
+        .arg(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut recv = || {
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        serde_json::from_str::<Value>(&line).unwrap()
+    };
+
+    let text = std::fs::read_to_string("data/sniff_stream.ndjson").unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut announced: Option<Value> = None;
+    for (i, &t) in ts.iter().enumerate() {
+        let mut ev: Value = serde_json::from_str(lines[i % lines.len()]).unwrap();
+        let m = ev.as_object_mut().unwrap();
+        m.insert("t".to_string(), t.into());
+        if i > 0 {
+            // The controller executes the announced action and leaves it out
+            // of the observation.
+            m.remove("sniff_strength");
+            m.remove("touch_pressure");
+        }
+        writeln!(stdin, "{}", ev).unwrap();
+        stdin.flush().unwrap();
+
+        let d = recv();
+        assert_eq!(d["kind"], "decision");
+        assert_eq!(d["t"], t);
+        if let Some(a) = &announced {
+            assert_eq!(d["action_source"], "planned");
+            assert_eq!(d["sniff_strength"], a["sniff_strength"]);
+            assert_eq!(d["touch_pressure"], a["touch_pressure"]);
+        }
+        let a = recv();
+        assert_eq!(a["kind"], "action");
+        assert_eq!(a["t"], t + 1);
+        announced = Some(a);
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn announced_action_drives_the_next_step() {
+    run_closed_loop("closed_loop", &[0, 1, 2, 3]);
+}
+
+#[test]
+fn announced_action_survives_gaps_in_t() {
+    run_closed_loop("closed_loop_gaps", &[0, 2, 5, 6, 10]);
+}
@@ -0,0 +1,31 @@
+// Helpers shared by the integration tests; not every test uses all of them.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Empty scratch directory, cleared of any earlier run's output.
+pub fn fresh_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Runs sniff_loop over the tactile stream into `fresh_dir(name)`, with extra
+// flags, and returns that directory.
+pub fn run_loop(name: &str, extra: &[&str]) -> PathBuf {
+    let dir = fresh_dir(name);
+    let out = Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .args(["--input", "data/sniff_stream_tactile.ndjson", "--out-dir"])
+        .arg(&dir)
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    dir
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use ndarray::array;
+use std::process::Command;
+
+use common::fresh_dir;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow};
+use llm_nature_experiential::policy::{choose_action_efe, efe_scores, EfeConfig, Forecast};
+use llm_nature_experiential::sensory::ActionParams;
+
+#[test]
+fn efe_trades_information_gain_against_effort() {
+    let q = array![0.25, 0.25, 0.25, 0.25];
+    let task = array![0.0, 1.0, 0.0, 0.0];
+    let cols = vec![vec![0.1, 0.7, 0.1, 0.1], vec![0.3, 0.1, 0.3, 0.3]];
+    let prior = |_: &ActionParams| q.clone();
+    let forecast = Forecast {
+        prior: &prior,
+        columns: &cols,
+        tactile: None,
+        w_olf: None,
+    };
+
+    // Without effort cost, the sharpest action is the most informative.
+    let free = EfeConfig {
+        effort_cost: 0.0,
+        ..Default::default()
+    };
+    let scores = efe_scores(&forecast, &task, &free);
+    assert_eq!(scores.len(), 30);
+    let weak = &scores[0];
+    let strong = scores.last().unwrap();
+    assert!(strong.epistemic > weak.epistemic);
+    assert!(scores.iter().all(|s| s.epistemic >= 0.0));
+
+    let (a, scores) = choose_action_efe(&forecast, &task, &EfeConfig::default());
+    let best = scores.iter().map(|s| s.efe).fold(f64::INFINITY, f64::min);
+    let chosen = scores
+        .iter()
+        .find(|s| s.sniff_strength == a.sniff_strength && s.touch_pressure == a.touch_pressure)
+        .unwrap();
+    assert_eq!(chosen.efe, best);
+    assert!(a.sniff_strength < 3.0 || a.touch_pressure < 2.0);
+}
+
+#[test]
+fn efe_scores_against_the_forecast_prior_and_fused_likelihood() {
+    let task = array![0.0, 1.0, 0.0, 0.0];
+    let cols = vec![vec![0.1, 0.7, 0.1, 0.1]];
+    let flat = |_: &ActionParams| array![0.25, 0.25, 0.25, 0.25];
+    let olf_only = Forecast {
+        prior: &flat,
+        columns: &cols,
+        tactile: None,
+        w_olf: None,
+    };
+    let base = efe_scores(&olf_only, &task, &EfeConfig::default());
+
+    // A flat tactile column at full weight leaves nothing to learn.
+    let tact = [0.25; 4];
+    let fused = Forecast {
+        tactile: Some(&tact),
+        w_olf: Some(0.0),
+        ..olf_only
+    };
+    let scores = efe_scores(&fused, &task, &EfeConfig::default());
+    assert!(base.iter().all(|s| s.epistemic > 1e-6));
+    assert!(scores.iter().all(|s| s.epistemic.abs() < 1e-12));
+
+    // A prior already sure of the cause leaves nothing to learn either.
+    let sure = |_: &ActionParams| array![0.0, 1.0, 0.0, 0.0];
+    let settled = Forecast {
+        prior: &sure,
+        ..olf_only
+    };
+    let scores = efe_scores(&settled, &task, &EfeConfig::default());
+    assert!(scores.iter().all(|s| s.epistemic.abs() < 1e-9));
+    assert!(scores.iter().all(|s| (s.pragmatic - 1.0).abs() < 1e-9));
+}
+
+#[test]
+fn sniff_loop_records_candidate_scores() {
+    let dir = fresh_dir("efe_policy");
+    let out = Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .args(["--input", "data/sniff_stream_policy_fire.ndjson"])
+        .args(["--policy", "efe"])
+        .arg("--out-dir")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let rows: Vec<ReplayRow> = LedgerReader::open(&dir.join("replay_loop.ndjson"))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rows[0].action_source, "policy");
+    assert_eq!(rows[0].policy.as_deref(), Some("efe"));
+    let diag = rows[0].policy_diagnostics.as_ref().unwrap();
+    let scores = diag["scores"].as_array().unwrap();
+    assert_eq!(scores.len(), 30);
+    // Without an A tensor the first event has no earlier column to learn
+    // from: its own observation is not known when the action is chosen.
+    assert!(scores.iter().all(|s| s["epistemic"] == 0.0));
+    // Events that carry their own action have nothing to score.
+    assert_eq!(rows[1].action_source, "event");
+    assert!(rows[1].policy_diagnostics.is_none());
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use std::process::Command;
+
+use common::fresh_dir;
+use llm_nature_experiential::event::StreamEvent;
+use llm_nature_experiential::validate::{check_event, ValidationMode, Validator, Verdict};
+
+fn event(t: u64) -> StreamEvent {
+    serde_json::from_str(&format!(
+        r#"{{"t":{},"o":[1,2],"A_shape":[4,3,5],"A_flat_col":[0.2,0.6,0.1,0.1],"p_prior":[0.4,0.2,0.2,0.2],"task_vec":[0.0,1.0,0.0,0.0]}}"#,
+        t
+    ))
+    .unwrap()
+}
+
+#[test]
+fn check_event_flags_lengths_values_and_time() {
+    assert!(check_event(&event(0), None, false).is_empty());
+
+    let mut ev = event(1);
+    ev.a_flat_col = vec![0.0, 0.0, 0.0, 0.0];
+    ev.task_vec = vec![0.0, 1.0];
+    ev.q0 = Some(vec![0.5, f64::NAN, 0.5, 0.0]);
+    let fields: Vec<&str> = check_event(&ev, Some(1), false)
+        .iter()
+        .map(|i| i.field)
+        .collect();
+    assert_eq!(fields, vec!["a_flat_col", "task_vec", "q0", "t"]);
+}
+
+#[test]
+fn validator_modes() {
+    let mut bad = event(0);
+    bad.a_flat_col[2] = -0.1;
+    bad.task_vec.push(0.0);
+
+    let mut fail = Validator::new(ValidationMode::FailFast, false);
+    let err = fail.admit(7, bad.clone()).err().unwrap().to_string();
+    assert!(err.starts_with("line 7: a_flat_col"), "{}", err);
+
+    let mut skip = Validator::new(ValidationMode::Skip, false);
+    assert!(matches!(
+        skip.admit(1, bad.clone()).unwrap(),
+        Verdict::Skipped(_)
+    ));
+    assert_eq!(skip.n_skipped, 1);
+
+    let mut repair = Validator::new(ValidationMode::Repair, false);
+    assert!(matches!(
+        repair.admit(1, event(5)).unwrap(),
+        Verdict::Accept(_)
+    ));
+    match repair.admit(2, bad).unwrap() {
+        Verdict::Repaired(ev, issues) => {
+            assert_eq!(issues.len(), 3);
+            assert_eq!(ev.a_flat_col, vec![0.2, 0.6, 0.0, 0.1]);
+            assert_eq!(ev.task_vec.len(), 4);
+            assert_eq!(ev.t, 6);
+        }
+        _ => panic!("expected repair"),
+    }
+
+    let mut short = event(10);
+    short.a_flat_col.pop();
+    assert!(matches!(
+        repair.admit(3, short).unwrap(),
+        Verdict::Skipped(_)
+    ));
+}
+
+#[test]
+fn belief_size_must_not_change() {
+    let mut small = event(1);
+    small.A_shape = vec![2, 3, 5];
+    small.a_flat_col = vec![0.7, 0.3];
+    small.p_prior = vec![0.5, 0.5];
+    small.task_vec = vec![1.0, 0.0];
+    assert!(check_event(&small, Some(0), false).is_empty());
+
+    let mut fail = Validator::new(ValidationMode::FailFast, false);
+    fail.admit(1, event(0)).unwrap();
+    let err = fail.admit(2, small.clone()).err().unwrap().to_string();
+    assert!(
+        err.starts_with("line 2: p_prior: length 2 changes the belief size 4"),
+        "{}",
+        err
+    );
+
+    let mut repair = Validator::new(ValidationMode::Repair, false);
+    repair.admit(1, event(0)).unwrap();
+    assert!(matches!(
+        repair.admit(2, small).unwrap(),
+        Verdict::Skipped(_)
+    ));
+    assert!(matches!(
+        repair.admit(3, event(1)).unwrap(),
+        Verdict::Accept(_)
+    ));
+    assert_eq!(repair.n_states, Some(4));
+}
+
+#[test]
+fn sniff_loop_skip_mode_reports_line_numbers() {
+    let good = std::fs::read_to_string("data/sniff_stream.ndjson").unwrap();
+    let mut lines: Vec<String> = good.lines().map(String::from).collect();
+    lines.insert(1, "{not json".to_string());
+    lines.push(
+        lines[0]
+            .replace(r#""t":0"#, r#""t":5"#)
+            .replace("0.6,0.1", "NaN,0.1"),
+    );
+    // A belief of a different size: skipped, not a panic in the kernel.
+    lines.push(
+        r#"{"t":6,"o":[1,2],"A_shape":[2,3,5],"A_flat_col":[0.7,0.3],"p_prior":[0.5,0.5],"task_vec":[1.0,0.0]}"#
+            .to_string(),
+    );
+
+    let dir = fresh_dir("event_validation");
+    let input = dir.join("bad_stream.ndjson");
+    std::fs::write(&input, lines.join("\n")).unwrap();
+
+    let run = |mode: &str| {
+        Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+            .arg("--input")
+            .arg(&input)
+            .args(["--dry-run", "--on-invalid", mode])
+            .output()
+            .unwrap()
+    };
+
+    let failed = run("fail");
+    assert!(!failed.status.success());
+    let stderr = String::from_utf8_lossy(&failed.stderr);
+    assert!(stderr.contains("line 2"), "{}", stderr);
+    assert!(!stderr.contains("skipped"), "{}", stderr);
+
+    let skipped = run("skip");
+    assert!(skipped.status.success());
+    let stderr = String::from_utf8_lossy(&skipped.stderr);
+    assert!(stderr.contains("skipped line 2"), "{}", stderr);
+    assert!(
+        stderr.contains("skipped line 5: p_prior: length 2 changes the belief size 4"),
+        "{}",
+        stderr
+    );
+    assert!(String::from_utf8_lossy(&skipped.stdout).contains("2 events"));
+}
+
+#[test]
+fn kernel_rejections_follow_the_validation_mode() {
+    // A_flat_col passes the schema checks but not the loaded tensor; its `t`
+    // must not block the valid t = 1 event after it.
+    let tensor = std::fs::read_to_string("data/sniff_stream_tensor.ndjson").unwrap();
+    let flat = std::fs::read_to_string("data/sniff_stream.ndjson").unwrap();
+    let mut lines: Vec<&str> = tensor.lines().collect();
+    lines.insert(1, flat.lines().nth(1).unwrap());
+
+    let dir = fresh_dir("kernel_rejections");
+    let input = dir.join("mixed_stream.ndjson");
+    std::fs::write(&input, lines.join("\n")).unwrap();
+
+    let run = |mode: &str| {
+        Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+            .arg("--input")
+            .arg(&input)
+            .args(["--dry-run", "--set", "a_tensor=\"data/A_tensor.json\""])
+            .args(["--on-invalid", mode])
+            .output()
+            .unwrap()
+    };
+
+    let failed = run("fail");
+    assert!(!failed.status.success());
+    let stderr = String::from_utf8_lossy(&failed.stderr);
+    assert!(stderr.contains("line 2"), "{}", stderr);
+    assert!(!stderr.contains("skipped"), "{}", stderr);
+
+    for mode in ["skip", "repair"] {
+        let out = run(mode);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(out.status.success(), "{}", stderr);
+        assert!(
+            stderr.contains("skipped line 2: event carries A_flat_col"),
+            "{}",
+            stderr
+        );
+        assert!(String::from_utf8_lossy(&out.stdout).contains("3 events"));
+    }
+}
@@ -0,0 +1,259 @@
+use llm_nature_experiential::event::StreamEvent;
+use llm_nature_experiential::kernel::{FilterMode, Kernel, KernelConfig};
+use llm_nature_experiential::transition::{TransitionConfig, TransitionIndex};
+
+fn event(t: u64, a_flat_col: Vec<f64>, q0: Option<Vec<f64>>) -> StreamEvent {
+    StreamEvent {
+        t,
+        o: vec![1, 2],
+        A_shape: vec![4, 3, 5],
+        a_flat_col,
+        tactile_flat_col: None,
+        p_prior: vec![0.4, 0.2, 0.2, 0.2],
+        task_vec: vec![0.0, 1.0, 0.0, 0.0],
+        q0,
+        sniff_strength: Some(1.2),
+        touch_pressure: Some(0.0),
+        action_id: None,
+    }
+}
+
+#[test]
+fn kernel_step_carries_belief_and_memory() {
+    let mut kernel = Kernel::new(KernelConfig::default()).unwrap();
+
+    let e0 = event(
+        0,
+        vec![0.2, 0.6, 0.1, 0.1],
+        Some(vec![0.35, 0.22, 0.25, 0.18]),
+    );
+    let out0 = kernel.step(&e0).unwrap();
+    assert_eq!(out0.trace.t, 0);
+    assert_eq!(out0.trace.o_idx, 7);
+    assert_eq!(out0.replay.q_before, vec![0.35, 0.22, 0.25, 0.18]);
+    assert_eq!(out0.trace.ignite_reason, out0.replay.ignite_reason);
+    assert_eq!(out0.replay.mem_window_len, 1);
+
+    let e1 = event(1, vec![0.25, 0.55, 0.10, 0.10], None);
+    let out1 = kernel.step(&e1).unwrap();
+    assert_eq!(out1.replay.q_before, out0.replay.q_next);
+    assert_eq!(out1.replay.mem_window_len, 2);
+}
+
+#[test]
+fn kernel_step_rejects_shape_mismatch() {
+    let mut kernel = Kernel::new(KernelConfig::default()).unwrap();
+    let mut ev = event(0, vec![0.2, 0.6, 0.1, 0.1], None);
+    ev.A_shape = vec![3, 3, 5];
+    assert!(kernel.step(&ev).is_err());
+}
+
+#[test]
+fn tactile_column_is_fused_and_recorded() {
+    let mut ev = event(0, vec![0.2, 0.6, 0.1, 0.1], None);
+    ev.tactile_flat_col = Some(vec![0.1, 0.3, 0.5, 0.1]);
+    ev.touch_pressure = Some(0.8);
+
+    let mut action_driven = Kernel::new(KernelConfig::default()).unwrap();
+    let out = action_driven.step(&ev).unwrap();
+    let w = out.replay.w_olf.unwrap();
+    assert!((w - 1.2 / (1.2 + 0.75 * 0.8)).abs() < 1e-12);
+    assert_eq!(out.replay.lik_olf, vec![0.2, 0.6, 0.1, 0.1]);
+    assert_eq!(out.replay.lik_tact, Some(vec![0.1, 0.3, 0.5, 0.1]));
+    assert!((out.replay.lik_fused.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+    let mut olf_only = Kernel::new(KernelConfig {
+        w_olf: Some(1.0),
+        ..KernelConfig::default()
+    })
+    .unwrap();
+    let out_olf = olf_only.step(&ev).unwrap();
+    ev.tactile_flat_col = None;
+    let out_none = Kernel::new(KernelConfig::default())
+        .unwrap()
+        .step(&ev)
+        .unwrap();
+    for (a, b) in out_olf.replay.q_after.iter().zip(&out_none.replay.q_after) {
+        assert!((a - b).abs() < 1e-9);
+    }
+    assert_eq!(out_none.replay.w_olf, None);
+}
+
+#[test]
+fn sequential_filter_accumulates_evidence() {
+    let cfg = KernelConfig {
+        filter: FilterMode::Sequential,
+        ..KernelConfig::default()
+    };
+    let mut seq = Kernel::new(cfg).unwrap();
+    let mut stat = Kernel::new(KernelConfig::default()).unwrap();
+
+    let mut last = (0.0, 0.0);
+    for t in 0..4 {
+        let ev = event(t, vec![0.2, 0.6, 0.1, 0.1], None);
+        let a = seq.step(&ev).unwrap();
+        let b = stat.step(&ev).unwrap();
+        for (p, q) in a.replay.prior.iter().zip(&a.replay.q_before) {
+            assert!((p - q).abs() < 1e-12);
+        }
+        assert_eq!(b.replay.prior, vec![0.4, 0.2, 0.2, 0.2]);
+        last = (a.replay.q_after[1], b.replay.q_after[1]);
+    }
+    // Repeating the same observation sharpens the sequential posterior only.
+    assert!(last.0 > last.1 + 0.1);
+    assert!(last.0 > 0.9);
+}
+
+#[test]
+fn transition_matrix_propagates_belief_before_update() {
+    let swap01 = vec![
+        vec![0.0, 1.0, 0.0, 0.0],
+        vec![1.0, 0.0, 0.0, 0.0],
+        vec![0.0, 0.0, 1.0, 0.0],
+        vec![0.0, 0.0, 0.0, 1.0],
+    ];
+    let identity: Vec<Vec<f64>> = (0..4)
+        .map(|i| (0..4).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let cfg = KernelConfig {
+        filter: FilterMode::Sequential,
+        transition: Some(TransitionConfig {
+            index: TransitionIndex::ActionId,
+            matrices: vec![identity, swap01],
+        }),
+        ..KernelConfig::default()
+    };
+    cfg.validate().unwrap();
+    let mut kernel = Kernel::new(cfg).unwrap();
+
+    let out0 = kernel
+        .step(&event(0, vec![0.2, 0.6, 0.1, 0.1], None))
+        .unwrap();
+    assert_eq!(out0.replay.transition_idx, Some(0));
+
+    let mut ev1 = event(1, vec![0.2, 0.6, 0.1, 0.1], None);
+    ev1.action_id = Some(1);
+    let out1 = kernel.step(&ev1).unwrap();
+    assert_eq!(out1.replay.transition_idx, Some(1));
+    let q = &out0.replay.q_next;
+    assert!((out1.replay.q_before[0] - q[1]).abs() < 1e-9);
+    assert!((out1.replay.q_before[1] - q[0]).abs() < 1e-9);
+
+    ev1.action_id = Some(2);
+    assert!(kernel.step(&ev1).is_err());
+}
+
+#[test]
+fn transition_changes_the_posterior_with_the_action() {
+    let stay = vec![
+        vec![1.0, 0.0, 0.0, 0.0],
+        vec![0.0, 1.0, 0.0, 0.0],
+        vec![0.0, 0.0, 1.0, 0.0],
+        vec![0.0, 0.0, 0.0, 1.0],
+    ];
+    let drift = vec![
+        vec![0.1, 0.0, 0.0, 0.9],
+        vec![0.9, 0.1, 0.0, 0.0],
+        vec![0.0, 0.9, 0.1, 0.0],
+        vec![0.0, 0.0, 0.9, 0.1],
+    ];
+    let cfg = KernelConfig {
+        filter: FilterMode::Sequential,
+        transition: Some(TransitionConfig {
+            index: TransitionIndex::ActionId,
+            matrices: vec![stay, drift],
+        }),
+        ..KernelConfig::default()
+    };
+    cfg.validate().unwrap();
+
+    let mut posteriors = Vec::new();
+    for action_id in [0, 1] {
+        let mut kernel = Kernel::new(cfg.clone()).unwrap();
+        kernel
+            .step(&event(0, vec![0.2, 0.6, 0.1, 0.1], None))
+            .unwrap();
+        let mut ev = event(1, vec![0.3, 0.3, 0.2, 0.2], None);
+        ev.action_id = Some(action_id);
+        posteriors.push(kernel.step(&ev).unwrap().replay.q_after);
+    }
+    let moved: f64 = posteriors[0]
+        .iter()
+        .zip(&posteriors[1])
+        .map(|(a, b)| (a - b).abs())
+        .sum();
+    assert!(moved > 0.1, "posteriors {:?}", posteriors);
+
+    // Without the sequential filter the prediction could not reach the update.
+    let static_cfg = KernelConfig {
+        filter: FilterMode::StaticPrior,
+        ..cfg
+    };
+    assert!(static_cfg.validate().is_err());
+}
+
+#[test]
+fn rejected_transition_leaves_the_kernel_untouched() {
+    let identity: Vec<Vec<f64>> = (0..4)
+        .map(|i| (0..4).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let mut kernel = Kernel::new(KernelConfig {
+        filter: FilterMode::Sequential,
+        transition: Some(TransitionConfig {
+            index: TransitionIndex::ActionId,
+            matrices: vec![identity],
+        }),
+        policy: "random".to_string(),
+        ..KernelConfig::default()
+    })
+    .unwrap();
+    kernel
+        .step(&event(0, vec![0.2, 0.6, 0.1, 0.1], None))
+        .unwrap();
+    let task = [0.0, 1.0, 0.0, 0.0];
+    kernel.plan_action(1, &task).unwrap();
+    let planned = kernel.planned.clone();
+    let last_lik = kernel.last_lik.clone();
+    let rng = kernel.policy.state();
+
+    let mut bad = event(1, vec![0.3, 0.3, 0.2, 0.2], None);
+    bad.action_id = Some(1);
+    bad.sniff_strength = None;
+    bad.touch_pressure = None;
+    assert!(kernel.step(&bad).is_err());
+    assert_eq!(kernel.planned, planned);
+    assert_eq!(kernel.last_lik, last_lik);
+    assert_eq!(kernel.policy.state(), rng);
+    assert_eq!(kernel.mem.rows().len(), 1);
+}
+
+#[test]
+fn transition_columns_must_be_distributions() {
+    let tr = TransitionConfig {
+        index: TransitionIndex::ActionId,
+        matrices: vec![vec![vec![0.5, 0.5], vec![0.6, 0.5]]],
+    };
+    assert!(tr.validate().is_err());
+}
+
+#[test]
+fn belief_size_change_is_an_error() {
+    let mut kernel = Kernel::new(KernelConfig::default()).unwrap();
+    kernel
+        .step(&event(0, vec![0.2, 0.6, 0.1, 0.1], None))
+        .unwrap();
+    let before = (kernel.q_state.clone(), kernel.mem.features(0));
+
+    let mut small = event(1, vec![0.7, 0.3], None);
+    small.A_shape = vec![2, 3, 5];
+    small.p_prior = vec![0.5, 0.5];
+    small.task_vec = vec![1.0, 0.0];
+    let err = kernel.step(&small).unwrap_err().to_string();
+    assert!(err.contains("carried belief has length 4"), "{}", err);
+    assert_eq!((kernel.q_state.clone(), kernel.mem.features(0)), before);
+
+    let mut short_task = event(1, vec![0.2, 0.6, 0.1, 0.1], None);
+    short_task.task_vec.pop();
+    let err = kernel.step(&short_task).unwrap_err().to_string();
+    assert!(err.contains("task_vec has length 3"), "{}", err);
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use std::io::Cursor;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::run_loop;
+use llm_nature_experiential::chain::{verify_chain, HashChain};
+use llm_nature_experiential::config::RunConfig;
+use llm_nature_experiential::ledger::{LedgerHeader, LedgerReader, LedgerTrailer, ReplayRow};
+
+fn ledger_verify(ledgers: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ledger_verify"))
+        .args(ledgers)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn chained_ledgers_verify_and_edits_are_caught() {
+    let dir = run_loop("ledger_chain", &["--hash-chain"]);
+    let trace = dir.join("trace_loop.ndjson");
+    let replay = dir.join("replay_loop.ndjson");
+
+    let ok = ledger_verify(&[&trace, &replay]);
+    assert!(
+        ok.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ok.stdout)
+    );
+    assert!(String::from_utf8_lossy(&ok.stdout).contains("OK (3 rows"));
+
+    // Chained rows still read as ordinary ledger rows.
+    let mut reader = LedgerReader::<_, ReplayRow>::open(&replay).unwrap();
+    assert!(reader.header().unwrap().config.hash_chain);
+    assert_eq!(reader.by_ref().count(), 3);
+    assert_eq!(reader.trailer().unwrap().rows, 3);
+
+    let text = std::fs::read_to_string(&replay).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+
+    // Edited value in the second row.
+    let edited = lines[2].replacen("\"ignited\":false", "\"ignited\":true", 1);
+    let edited = if edited == lines[2] {
+        lines[2].replacen("\"ignited\":true", "\"ignited\":false", 1)
+    } else {
+        edited
+    };
+    let mut tampered = lines.clone();
+    tampered[2] = &edited;
+    let err = verify_chain(Cursor::new(tampered.join("\n"))).unwrap_err();
+    assert!(err.to_string().contains("line 3"), "{}", err);
+
+    // Dropped row breaks the link of the next one.
+    let mut dropped = lines.clone();
+    dropped.remove(2);
+    let err = verify_chain(Cursor::new(dropped.join("\n"))).unwrap_err();
+    assert!(err.to_string().contains("prev_hash"), "{}", err);
+
+    // Trailing rows cut off, down to the header alone: the trailer is missing.
+    for keep in [4, 2, 1] {
+        let cut = lines[..keep].join("\n");
+        let err = verify_chain(Cursor::new(cut)).unwrap_err();
+        assert!(err.to_string().contains("no trailer"), "{}", err);
+    }
+    let cut = dir.join("cut.ndjson");
+    std::fs::write(&cut, lines[..2].join("\n")).unwrap();
+    let failed = ledger_verify(&[&cut]);
+    assert!(!failed.status.success());
+    let stdout = String::from_utf8_lossy(&failed.stdout);
+    assert!(
+        stdout.contains("FAILED: no trailer after line 2"),
+        "{}",
+        stdout
+    );
+
+    // Nothing may follow the trailer.
+    let mut extra = lines.clone();
+    extra.push(lines[2]);
+    let err = verify_chain(Cursor::new(extra.join("\n"))).unwrap_err();
+    assert!(err.to_string().contains("follows the trailer"), "{}", err);
+
+    let bad = dir.join("tampered.ndjson");
+    std::fs::write(&bad, tampered.join("\n")).unwrap();
+    let failed = ledger_verify(&[&trace, &bad]);
+    assert!(!failed.status.success());
+    assert!(String::from_utf8_lossy(&failed.stdout).contains("FAILED"));
+}
+
+#[test]
+fn header_config_must_match_config_hash() {
+    let cfg = RunConfig {
+        hash_chain: true,
+        ..RunConfig::default()
+    };
+    let mut header = LedgerHeader::new("trace", &cfg);
+
+    let mut chain = HashChain::new();
+    let sealed = chain.seal(&header).unwrap().to_string();
+    let trailer = LedgerTrailer::new(0, chain.last_hash());
+    let good = format!("{}\n{}", sealed, chain.seal(&trailer).unwrap());
+    assert_eq!(verify_chain(Cursor::new(good)).unwrap().rows, 0);
+
+    // A resealed header whose config no longer matches its recorded hash.
+    header.config.kernel.rg_cost = 0.5;
+    let mut chain = HashChain::new();
+    let forged = chain.seal(&header).unwrap().to_string();
+    let trailer = LedgerTrailer::new(0, chain.last_hash());
+    let forged = format!("{}\n{}", forged, chain.seal(&trailer).unwrap());
+    let err = verify_chain(Cursor::new(forged)).unwrap_err();
+    assert!(err.to_string().contains("config_hash"), "{}", err);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::run_loop;
+use llm_nature_experiential::diff::diff_ledgers;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow};
+
+fn rows(path: &Path) -> Vec<ReplayRow> {
+    LedgerReader::<_, ReplayRow>::open(path)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+#[test]
+fn stricter_delta_flips_ignition() {
+    let base = run_loop("ledger_diff_base", &[]).join("replay_loop.ndjson");
+    let strict =
+        run_loop("ledger_diff_strict", &["--set", "params.delta=1.0"]).join("replay_loop.ndjson");
+    let (a, b) = (rows(&base), rows(&strict));
+
+    let same = diff_ledgers(&a, &a);
+    assert_eq!(same.aligned, 3);
+    assert!(same.flips.is_empty());
+    assert!(same.max_dev.iter().all(|m| m.abs_diff == 0.0));
+
+    let d = diff_ledgers(&a, &b);
+    assert_eq!(d.aligned, 3);
+    let ignited_in_a = a.iter().filter(|r| r.ignited).count();
+    assert_eq!(d.flips.len(), ignited_in_a);
+    assert!(d
+        .flips
+        .iter()
+        .all(|f| f.ignited_a && f.reason_b == "deltaG_fail"));
+    assert_eq!(d.ignite_reasons["ignite"].shift, -(ignited_in_a as f64));
+    assert_eq!(d.max_dev[0].field, "q_next");
+
+    // Unmatched steps are listed and left out of the comparison.
+    let d = diff_ledgers(&a, &b[..2]);
+    assert_eq!(
+        (d.aligned, d.only_a.clone(), d.only_b.len()),
+        (2, vec![2], 0)
+    );
+
+    let cli = Command::new(env!("CARGO_BIN_EXE_ledger_diff"))
+        .arg(&base)
+        .arg(&strict)
+        .arg("--fail-on-flip")
+        .output()
+        .unwrap();
+    assert!(!cli.status.success());
+    assert!(String::from_utf8_lossy(&cli.stdout).contains("ignite -> deltaG_fail"));
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+
+use common::run_loop;
+use llm_nature_experiential::export::{read_columnar, Cell, DType, Layout, Table};
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow};
+
+fn export(ledger: &Path, args: &[&str]) -> Vec<u8> {
+    let out = Command::new(env!("CARGO_BIN_EXE_ledger_export"))
+        .arg(ledger)
+        .args(["--out", "-"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out.stdout
+}
+
+#[test]
+fn wide_csv_spreads_vectors_over_indexed_columns() {
+    let dir = run_loop("ledger_export_csv", &[]);
+    let csv = String::from_utf8(export(&dir.join("trace_loop.ndjson"), &[])).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header[0], "t");
+    for c in ["q_after_0", "q_after_3", "dx_0", "ignite_reason"] {
+        assert!(header.contains(&c), "missing column {}", c);
+    }
+    assert!(lines[1..]
+        .iter()
+        .all(|l| l.split(',').count() == header.len()));
+
+    let long = String::from_utf8(export(
+        &dir.join("replay_loop.ndjson"),
+        &["--layout", "long"],
+    ))
+    .unwrap();
+    assert!(long.starts_with("t,field,index,value\n"));
+    assert!(long.lines().any(|l| l.starts_with("2,q_next,3,")));
+    assert!(!long.contains("ignite_reason"));
+}
+
+#[test]
+fn columnar_export_round_trips() {
+    let dir = run_loop("ledger_export_columnar", &[]);
+    let ledger = dir.join("replay_loop.ndjson");
+    let rows: Vec<ReplayRow> = LedgerReader::<_, ReplayRow>::open(&ledger)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let expected = Table::build(&rows, Layout::Wide).unwrap();
+
+    let bytes = export(&ledger, &["--format", "columnar"]);
+    let table = read_columnar(&mut Cursor::new(bytes)).unwrap();
+    assert_eq!(table.n_rows(), 3);
+    assert_eq!(table.columns.len(), expected.columns.len());
+
+    let t = table.column("t").unwrap();
+    assert_eq!(t.dtype(), DType::I64);
+    assert_eq!(t.cells, vec![Cell::Int(0), Cell::Int(1), Cell::Int(2)]);
+    assert_eq!(table.column("ignited").unwrap().dtype(), DType::Bool);
+    assert_eq!(
+        table.column("q_next_1").unwrap().cells,
+        expected.column("q_next_1").unwrap().cells
+    );
+    assert_eq!(
+        table.column("ignite_reason").unwrap().cells,
+        expected.column("ignite_reason").unwrap().cells
+    );
+}
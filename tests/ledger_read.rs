@@ -0,0 +1,68 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::run_loop;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow, RowFilter, TraceRow};
+
+#[test]
+fn reads_back_headered_ledgers() {
+    let dir = run_loop("ledger_read_headered", &[]);
+
+    let trace = LedgerReader::<_, TraceRow>::open(&dir.join("trace_loop.ndjson")).unwrap();
+    assert_eq!(trace.schema_version(), 7);
+    assert_eq!(trace.header().unwrap().ledger, "trace");
+    let rows: Vec<TraceRow> = trace.map(|r| r.unwrap()).collect();
+    assert_eq!(rows.iter().map(|r| r.t).collect::<Vec<_>>(), vec![0, 1, 2]);
+
+    let filtered: Vec<ReplayRow> =
+        LedgerReader::<_, ReplayRow>::open(&dir.join("replay_loop.ndjson"))
+            .unwrap()
+            .with_filter(RowFilter {
+                t_min: Some(1),
+                t_max: Some(2),
+                ignite_reason: Some(rows[1].ignite_reason.clone()),
+            })
+            .map(|r| r.unwrap())
+            .collect();
+    assert!(!filtered.is_empty());
+    assert!(filtered
+        .iter()
+        .all(|r| r.t >= 1 && r.ignite_reason == rows[1].ignite_reason));
+
+    // Wrong ledger kind is rejected.
+    assert!(LedgerReader::<_, ReplayRow>::open(&dir.join("trace_loop.ndjson")).is_err());
+}
+
+#[test]
+fn truncated_tail_is_tolerated_but_mid_file_garbage_is_not() {
+    let dir = run_loop("ledger_read_truncated", &[]);
+    let text = std::fs::read_to_string(dir.join("replay_loop.ndjson")).unwrap();
+
+    let cut = &text[..text.len() - 40];
+    let mut reader = LedgerReader::<_, ReplayRow>::new(Cursor::new(cut.to_string())).unwrap();
+    let rows: Vec<ReplayRow> = reader.by_ref().map(|r| r.unwrap()).collect();
+    assert_eq!(rows.len(), 2);
+    assert!(reader.truncated());
+
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines.insert(2, "{garbage");
+    let mid = lines.join("\n");
+    let results: Vec<_> = LedgerReader::<_, ReplayRow>::new(Cursor::new(mid))
+        .unwrap()
+        .collect();
+    let err = results.iter().find_map(|r| r.as_ref().err()).unwrap();
+    assert!(format!("{:#}", err).starts_with("line 3"));
+}
+
+#[test]
+fn headerless_ledger_is_schema_v1() {
+    let dir = run_loop("ledger_read_legacy", &[]);
+    let text = std::fs::read_to_string(dir.join("trace_loop.ndjson")).unwrap();
+    let body: String = text.lines().skip(1).map(|l| format!("{}\n", l)).collect();
+
+    let reader = LedgerReader::<_, TraceRow>::new(Cursor::new(body)).unwrap();
+    assert_eq!(reader.schema_version(), 1);
+    assert!(reader.header().is_none());
+    assert_eq!(reader.count(), 3);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::process::Command;
+
+use common::run_loop;
+use llm_nature_experiential::ledger::{LedgerReader, ReplayRow, TraceRow};
+use llm_nature_experiential::summary::{percentile, RunSummary, Stats};
+
+#[test]
+fn percentiles_interpolate_between_ranks() {
+    let v = [1.0, 2.0, 3.0, 4.0, 5.0];
+    assert_eq!(percentile(&v, 0.5), 3.0);
+    assert!((percentile(&v, 0.1) - 1.4).abs() < 1e-12);
+    let s = Stats::from_values(&[3.0, 1.0, 2.0]).unwrap();
+    assert_eq!((s.min, s.p50, s.max, s.mean), (1.0, 2.0, 3.0, 2.0));
+    assert!(Stats::from_values(&[]).is_none());
+}
+
+#[test]
+fn summary_counts_match_ledgers() {
+    let dir = run_loop("ledger_summarize", &[]);
+    let trace_path = dir.join("trace_loop.ndjson");
+    let replay_path = dir.join("replay_loop.ndjson");
+
+    let trace: Vec<TraceRow> = LedgerReader::<_, TraceRow>::open(&trace_path)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let replay: Vec<ReplayRow> = LedgerReader::<_, ReplayRow>::open(&replay_path)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    let s = RunSummary::new(&trace, &replay);
+
+    assert_eq!(s.steps, 3);
+    assert_eq!(s.ignite_reasons.len(), 4);
+    assert_eq!(s.ignite_reasons.values().sum::<usize>(), 3);
+    assert_eq!(s.ignited, trace.iter().filter(|r| r.ignited).count());
+    assert_eq!(
+        s.coherence_hist
+            .as_ref()
+            .unwrap()
+            .counts
+            .iter()
+            .sum::<usize>(),
+        3
+    );
+    assert_eq!(s.action_source.values().sum::<usize>(), 3);
+    assert!(s.temperature.is_some());
+
+    // Replay alone still gives ignition statistics, but no coherence.
+    let r = RunSummary::new(&[], &replay);
+    assert_eq!(r.ignite_reasons, s.ignite_reasons);
+    assert!(r.coherence.is_none());
+
+    let cli = Command::new(env!("CARGO_BIN_EXE_ledger_summarize"))
+        .arg(&trace_path)
+        .arg(&replay_path)
+        .arg("--json")
+        .output()
+        .unwrap();
+    assert!(cli.status.success());
+    let from_cli: RunSummary = serde_json::from_slice(&cli.stdout).unwrap();
+    assert_eq!(from_cli, s);
+
+    let text = Command::new(env!("CARGO_BIN_EXE_ledger_summarize"))
+        .arg(&trace_path)
+        .arg(&replay_path)
+        .output()
+        .unwrap();
+    let text = String::from_utf8(text.stdout).unwrap();
+    assert!(text.contains("ignition rate"));
+    assert!(text.contains("coherence_fail"));
+}
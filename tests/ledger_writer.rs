@@ -0,0 +1,77 @@
+mod common;
+
+use std::path::Path;
+
+use common::run_loop;
+use llm_nature_experiential::config::RunConfig;
+use llm_nature_experiential::finite::check_finite;
+use llm_nature_experiential::ledger::{
+    partial_path, LedgerHeader, LedgerReader, LedgerWriter, TraceRow, WriterOptions,
+};
+
+fn trace_rows(dir: &Path) -> Vec<TraceRow> {
+    assert!(!partial_path(&dir.join("trace_loop.ndjson")).exists());
+    LedgerReader::<_, TraceRow>::open(&dir.join("trace_loop.ndjson"))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+#[test]
+fn rows_appear_on_flush_and_ledger_on_finish() {
+    let dir = run_loop("ledger_writer_flush", &[]);
+    let rows = trace_rows(&dir);
+    let path = dir.join("copy.ndjson");
+    let partial = partial_path(&path);
+
+    let header = LedgerHeader::new("trace", &RunConfig::default());
+    let opts = WriterOptions {
+        flush_every: 2,
+        fsync: true,
+    };
+    let mut w = LedgerWriter::create_with(&path, &header, opts).unwrap();
+    for r in &rows {
+        w.write_row(r).unwrap();
+    }
+    assert_eq!(w.rows(), 3);
+    assert!(!path.exists());
+    // Header and the first two rows were flushed; the third is still buffered.
+    let on_disk = std::fs::read_to_string(&partial).unwrap();
+    assert_eq!(on_disk.lines().count(), 3);
+
+    assert_eq!(w.finish().unwrap(), path);
+    assert!(!partial.exists());
+    let back: Vec<TraceRow> = LedgerReader::<_, TraceRow>::open(&path)
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(back.len(), rows.len());
+    assert_eq!(back[2].q_after, rows[2].q_after);
+}
+
+#[test]
+fn non_finite_rows_are_rejected_and_ledger_is_not_finished() {
+    let dir = run_loop("ledger_writer_nan", &[]);
+    let mut rows = trace_rows(&dir);
+    rows[1].q_after[2] = f64::NAN;
+
+    let err = check_finite(&rows[1]).unwrap_err();
+    assert_eq!(err.0, "q_after[2] = NaN");
+    assert!(check_finite(&rows[0]).is_ok());
+
+    let path = dir.join("nan.ndjson");
+    let header = LedgerHeader::new("trace", &RunConfig::default());
+    let mut w = LedgerWriter::create(&path, &header).unwrap();
+    w.write_row(&rows[0]).unwrap();
+    let err = w.write_row(&rows[1]).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("q_after[2] = NaN"),
+        "{:#}",
+        err
+    );
+    drop(w);
+
+    // Only the partial file is left behind.
+    assert!(!path.exists());
+    assert!(partial_path(&path).exists());
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use llm_nature_experiential::event::StreamEvent;
+use llm_nature_experiential::kernel::{Kernel, KernelConfig};
+use llm_nature_experiential::likelihood::LikelihoodTensor;
+
+fn npy_bytes(shape: &[usize], data: &[f64]) -> Vec<u8> {
+    let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': ({},), }}",
+        dims.join(", ")
+    );
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for v in data {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out
+}
+
+fn tensor_event(o: Vec<usize>) -> StreamEvent {
+    serde_json::from_value(serde_json::json!({
+        "t": 0,
+        "o": o,
+        "p_prior": [0.4, 0.2, 0.2, 0.2],
+        "task_vec": [0.0, 1.0, 0.0, 0.0],
+        "sniff_strength": 1.2,
+        "touch_pressure": 0.0
+    }))
+    .unwrap()
+}
+
+#[test]
+fn json_and_npy_tensors_slice_the_same_column() {
+    let a = LikelihoodTensor::load(Path::new("data/A_tensor.json")).unwrap();
+    assert_eq!(a.shape, vec![4, 3, 5]);
+
+    let npy = Path::new(env!("CARGO_TARGET_TMPDIR")).join("A_tensor.npy");
+    std::fs::write(&npy, npy_bytes(&a.shape, &a.data)).unwrap();
+    let b = LikelihoodTensor::load(&npy).unwrap();
+    assert_eq!(a.data, b.data);
+
+    let (o_idx, col) = a.column(&[1, 2]).unwrap();
+    assert_eq!(o_idx, 7);
+    assert_eq!(col, (0..4).map(|s| a.data[s * 15 + 7]).collect::<Vec<_>>());
+
+    assert!(a.column(&[3, 0]).is_err());
+    assert!(a.column(&[1]).is_err());
+}
+
+#[test]
+fn unnormalized_tensor_is_rejected() {
+    let bad = LikelihoodTensor {
+        shape: vec![2, 2],
+        data: vec![0.5, 0.5, 0.7, 0.7],
+    };
+    assert!(bad.validate().is_err());
+}
+
+#[test]
+fn kernel_slices_tensor_column_like_an_explicit_flat_col() {
+    let cfg = KernelConfig {
+        a_tensor: Some("data/A_tensor.json".into()),
+        ..KernelConfig::default()
+    };
+    let mut with_tensor = Kernel::from_config(cfg).unwrap();
+    let out = with_tensor.step(&tensor_event(vec![1, 2])).unwrap();
+
+    let mut explicit = tensor_event(vec![1, 2]);
+    explicit.A_shape = vec![4, 3, 5];
+    explicit.a_flat_col = out.replay.lik_olf.clone();
+    let out2 = Kernel::new(KernelConfig::default())
+        .unwrap()
+        .step(&explicit)
+        .unwrap();
+    assert_eq!(out.replay.q_after, out2.replay.q_after);
+    assert_eq!(out.trace.o_idx, out2.trace.o_idx);
+
+    assert!(with_tensor.step(&tensor_event(vec![1, 5])).is_err());
+    assert!(with_tensor.step(&explicit).is_err());
+}
@@ -0,0 +1,87 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use std::process::{Command, Stdio};
+
+use common::fresh_dir;
+use serde_json::Value;
+
+fn events() -> Vec<String> {
+    std::fs::read_to_string("data/sniff_stream.ndjson")
+        .unwrap()
+        .lines()
+        .map(|l| format!("{}\n", l))
+        .collect()
+}
+
+#[test]
+fn rows_arrive_before_the_next_event_is_sent() {
+    let dir = fresh_dir("live_stdio");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .args(["--input", "-", "--stream-out", "-", "--emit", "decision"])
+        .arg("--out-dir")
+        .arg(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    let events = events();
+    for (i, ev) in events.iter().enumerate() {
+        stdin.write_all(ev.as_bytes()).unwrap();
+        stdin.flush().unwrap();
+        // Blocks until the step's decision is out; stdin stays open.
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        let v: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(v["kind"], "decision");
+        assert_eq!(v["t"], i as u64);
+        assert!(v["ignite_reason"].is_string());
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    let ledger = std::fs::read_to_string(dir.join("replay_loop.ndjson")).unwrap();
+    assert_eq!(ledger.lines().count(), events.len() + 1);
+}
+
+#[test]
+fn unix_sockets_carry_events_and_rows() {
+    let dir = fresh_dir("live_unix");
+    let in_sock = dir.join("events.sock");
+    let out_sock = dir.join("rows.sock");
+    let events_listener = UnixListener::bind(&in_sock).unwrap();
+    let rows_listener = UnixListener::bind(&out_sock).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sniff_loop"))
+        .arg("--input")
+        .arg(format!("unix:{}", in_sock.display()))
+        .arg("--stream-out")
+        .arg(format!("unix:{}", out_sock.display()))
+        .arg("--dry-run")
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let (mut ev_conn, _) = events_listener.accept().unwrap();
+    let (row_conn, _) = rows_listener.accept().unwrap();
+    let mut rows = BufReader::new(row_conn);
+    let events = events();
+    for ev in &events {
+        ev_conn.write_all(ev.as_bytes()).unwrap();
+        for kind in ["trace", "replay"] {
+            let mut line = String::new();
+            rows.read_line(&mut line).unwrap();
+            let v: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(v["kind"], kind);
+        }
+    }
+    drop(ev_conn);
+    assert!(child.wait().unwrap().success());
+}
@@ -0,0 +1,81 @@
+use llm_nature_experiential::kernel::{Kernel, KernelConfig};
+use llm_nature_experiential::memory::{MemoryMode, MemoryRow, MemoryState};
+
+fn row(t: u64) -> MemoryRow {
+    let x = t as f64;
+    MemoryRow {
+        t,
+        ignited: t.is_multiple_of(3),
+        d_g_broadcast: (0.3 * x).sin(),
+        temperature: 1.0 + 0.01 * x,
+        sniff_strength: 1.0 + 0.5 * (0.7 * x).cos(),
+        touch_pressure: 0.2 + 0.05 * x,
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * (1.0 + b.abs())
+}
+
+#[test]
+fn decayed_features_match_weighted_recomputation() {
+    let half_life = 5.0;
+    let mut mem = MemoryState::with_half_life(half_life);
+    let rows: Vec<MemoryRow> = (0..200).map(row).collect();
+    for r in &rows {
+        mem.push(r.clone());
+    }
+    assert!(mem.rows().len() <= mem.last_k);
+    let f = mem.features(200);
+
+    // Brute force: weight 0.5^(age / half_life), age 0 for the newest row.
+    let n = rows.len();
+    let w: Vec<f64> = (0..n)
+        .map(|i| 0.5f64.powf((n - 1 - i) as f64 / half_life))
+        .collect();
+    let sw: f64 = w.iter().sum();
+    let wmean =
+        |f: fn(&MemoryRow) -> f64| rows.iter().zip(&w).map(|(r, w)| w * f(r)).sum::<f64>() / sw;
+    let m = wmean(|r| r.sniff_strength);
+    let var = wmean(|r| r.sniff_strength * r.sniff_strength) - m * m;
+    let ess = sw * sw / w.iter().map(|w| w * w).sum::<f64>();
+
+    let ignite = rows
+        .iter()
+        .zip(&w)
+        .filter(|(r, _)| r.ignited)
+        .map(|(_, w)| w)
+        .sum::<f64>()
+        / sw;
+    assert!(close(f.ignite_rate, ignite));
+    assert!(close(f.mean_sniff_strength, m));
+    assert!(close(f.mean_d_g_broadcast, wmean(|r| r.d_g_broadcast)));
+    assert!(close(f.var_sniff_strength, var));
+    assert!(close(f.effective_n, ess));
+    assert_eq!(f.window_len, n);
+    // touch_pressure rises by 0.05 per row.
+    assert!(close(f.trend_touch_pressure, 0.05));
+    assert_eq!(mem.mem_window_len(), n);
+}
+
+#[test]
+fn kernel_runs_with_decaying_memory() {
+    let cfg = KernelConfig {
+        mem_mode: MemoryMode::Decay,
+        mem_half_life: 2.0,
+        ..Default::default()
+    };
+    cfg.validate().unwrap();
+    let mut kernel = Kernel::new(cfg).unwrap();
+    let text = std::fs::read_to_string("data/sniff_stream.ndjson").unwrap();
+    let mut last = None;
+    for line in text.lines() {
+        let ev = serde_json::from_str(line).unwrap();
+        last = Some(kernel.step(&ev).unwrap());
+    }
+    let out = last.unwrap();
+    assert_eq!(out.replay.mem_window_len, text.lines().count());
+    let f = kernel.mem.features(out.replay.t);
+    assert!(f.effective_n < f.window_len as f64);
+    assert!(f.effective_n > 1.0);
+}
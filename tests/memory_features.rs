@@ -0,0 +1,76 @@
+use ndarray::array;
+
+use llm_nature_experiential::memory::{MemoryRow, MemoryState, MEMORY_FEATURES_VERSION};
+use llm_nature_experiential::policy::{
+    choose_action, MemoryStats, Policy, PolicyInput, SmoothConfig, SmoothPolicy,
+};
+
+fn row(t: u64, sniff: f64, touch: f64) -> MemoryRow {
+    MemoryRow {
+        t,
+        ignited: t.is_multiple_of(2),
+        d_g_broadcast: 0.1 * t as f64,
+        temperature: 1.0,
+        sniff_strength: sniff,
+        touch_pressure: touch,
+    }
+}
+
+#[test]
+fn features_report_spread_trend_and_last_k() {
+    let mut mem = MemoryState::new(16);
+    mem.last_k = 4;
+    for t in 0..6u64 {
+        let sniff = if t.is_multiple_of(2) { 1.0 } else { 2.0 };
+        mem.push(row(t, sniff, 0.5 * t as f64));
+    }
+    let f = mem.features(6);
+    assert_eq!(f.version, MEMORY_FEATURES_VERSION);
+    assert_eq!(f.mem_stats_version(), MEMORY_FEATURES_VERSION);
+    assert_eq!(f.effective_n, 6.0);
+    assert!(f.mem_features().is_some());
+
+    assert!((f.var_sniff_strength - 0.25).abs() < 1e-12);
+    assert_eq!(f.var_temperature, 0.0);
+    assert!((f.trend_touch_pressure - 0.5).abs() < 1e-12);
+    assert!((f.trend_d_g_broadcast - 0.1).abs() < 1e-12);
+
+    assert_eq!(f.recent.k, 4);
+    assert_eq!(f.recent.sniff_reversals, 2);
+    assert_eq!(f.recent.touch_reversals, 0);
+    assert_eq!(f.recent.last_sniff_strength, Some(2.0));
+    assert_eq!(f.recent.ignite_rate, 0.5);
+}
+
+#[test]
+fn smooth_policy_damps_oscillating_actions() {
+    let q = array![0.25, 0.25, 0.25, 0.25];
+    let task = array![0.0, 0.0, 0.0, 0.0];
+    let mut mem = MemoryState::new(16);
+    for t in 0..6u64 {
+        let sniff = if t.is_multiple_of(2) { 0.5 } else { 1.5 };
+        mem.push(row(t, sniff, 1.0));
+    }
+    let f = mem.features(6);
+    let target = choose_action(&q, &f, &task);
+    let mut smooth = SmoothPolicy {
+        cfg: SmoothConfig::default(),
+    };
+    let out = smooth.choose(&PolicyInput {
+        q_state: &q,
+        mem: &f,
+        task: &task,
+        t: 6,
+        forecast: None,
+    });
+
+    // Sniff keeps reversing, so it only moves part of the way and at most
+    // `max_step`; touch is steady and may take a full step.
+    let moved = (out.action.sniff_strength - 1.5).abs();
+    assert!(moved <= 0.5 + 1e-12);
+    assert!(moved < (target.sniff_strength - 1.5).abs());
+    assert!(out.action.sniff_strength <= 3.0 - 0.1);
+    let diag = out.diagnostics.unwrap();
+    assert_eq!(diag["damped_sniff"], true);
+    assert_eq!(diag["damped_touch"], false);
+}
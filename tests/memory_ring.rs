@@ -0,0 +1,167 @@
+use llm_nature_experiential::memory::{MemoryFeatures, MemoryRow, MemoryState};
+
+// The scan-everything implementation the ring buffer replaced, verbatim.
+struct Reference {
+    window_max: usize,
+    rows: Vec<MemoryRow>,
+}
+
+fn mean_var(xs: impl Iterator<Item = f64>, n: f64) -> (f64, f64) {
+    let (sum, sum_sq) = xs.fold((0.0, 0.0), |(s, q), x| (s + x, q + x * x));
+    let mean = sum / n;
+    (mean, (sum_sq / n - mean * mean).max(0.0))
+}
+
+fn trend(xs: impl Iterator<Item = f64>, n: usize) -> f64 {
+    if n < 2 {
+        return 0.0;
+    }
+    let (sum_x, sum_ix) = xs
+        .enumerate()
+        .fold((0.0, 0.0), |(s, si), (i, x)| (s + x, si + i as f64 * x));
+    let n = n as f64;
+    let sum_i = n * (n - 1.0) / 2.0;
+    let sum_ii = (n - 1.0) * n * (2.0 * n - 1.0) / 6.0;
+    (n * sum_ix - sum_i * sum_x) / (n * sum_ii - sum_i * sum_i)
+}
+
+impl Reference {
+    fn push(&mut self, row: MemoryRow) {
+        self.rows.push(row);
+        if self.rows.len() > self.window_max {
+            let overflow = self.rows.len() - self.window_max;
+            self.rows.drain(0..overflow);
+        }
+    }
+
+    fn features(&self, t: u64, mem: &MemoryState) -> MemoryFeatures {
+        let n = self.rows.len().max(1) as f64;
+        let col = |f: fn(&MemoryRow) -> f64| self.rows.iter().map(f);
+        let len = self.rows.len();
+        MemoryFeatures {
+            version: mem.features(t).version,
+            t,
+            window_len: len,
+            effective_n: len as f64,
+            ignite_rate: self.rows.iter().filter(|r| r.ignited).count() as f64 / n,
+            mean_d_g_broadcast: col(|r| r.d_g_broadcast).sum::<f64>() / n,
+            mean_temperature: col(|r| r.temperature).sum::<f64>() / n,
+            mean_sniff_strength: col(|r| r.sniff_strength).sum::<f64>() / n,
+            mean_touch_pressure: col(|r| r.touch_pressure).sum::<f64>() / n,
+            var_d_g_broadcast: mean_var(col(|r| r.d_g_broadcast), n).1,
+            var_temperature: mean_var(col(|r| r.temperature), n).1,
+            var_sniff_strength: mean_var(col(|r| r.sniff_strength), n).1,
+            var_touch_pressure: mean_var(col(|r| r.touch_pressure), n).1,
+            trend_d_g_broadcast: trend(col(|r| r.d_g_broadcast), len),
+            trend_sniff_strength: trend(col(|r| r.sniff_strength), len),
+            trend_touch_pressure: trend(col(|r| r.touch_pressure), len),
+            // The last-k block is computed from the rows either way.
+            recent: mem.recent(),
+        }
+    }
+}
+
+// Bit patterns of the statistics, so -0.0 and 0.0 count as different.
+fn bits(f: &MemoryFeatures) -> [u64; 12] {
+    [
+        f.ignite_rate,
+        f.mean_d_g_broadcast,
+        f.mean_temperature,
+        f.mean_sniff_strength,
+        f.mean_touch_pressure,
+        f.var_d_g_broadcast,
+        f.var_temperature,
+        f.var_sniff_strength,
+        f.var_touch_pressure,
+        f.trend_d_g_broadcast,
+        f.trend_sniff_strength,
+        f.trend_touch_pressure,
+    ]
+    .map(f64::to_bits)
+}
+
+fn assert_same(got: &MemoryFeatures, want: &MemoryFeatures, what: &str) {
+    assert_eq!(got, want, "{}", what);
+    assert_eq!(bits(got), bits(want), "{}", what);
+}
+
+// Deterministic rows with values spanning several orders of magnitude.
+fn row(t: u64) -> MemoryRow {
+    let x = t as f64;
+    MemoryRow {
+        t,
+        ignited: (t * 7919) % 5 < 2,
+        d_g_broadcast: (0.37 * x).sin() * 10f64.powi((t % 5) as i32 - 2),
+        temperature: 0.25 + 3.75 * (0.11 * x).cos().abs(),
+        sniff_strength: 0.1 + 2.9 * (0.53 * x).sin().abs(),
+        touch_pressure: 3.0 * (0.29 * x).cos().powi(2),
+    }
+}
+
+#[test]
+fn ring_buffer_matches_full_rescan() {
+    for window in [1, 2, 7, 64] {
+        let mut mem = MemoryState::new(window);
+        let mut reference = Reference {
+            window_max: window,
+            rows: Vec::new(),
+        };
+        for t in 0..2_000u64 {
+            let want = reference.features(t, &mem);
+            let what = format!("window {} t {}", window, t);
+            assert_same(&mem.features(t), &want, &what);
+            assert_eq!(mem.mem_ignite_rate(), want.ignite_rate);
+            assert_eq!(
+                mem.mem_mean_dg_broadcast().to_bits(),
+                want.mean_d_g_broadcast.to_bits()
+            );
+            mem.push(row(t));
+            reference.push(row(t));
+        }
+        let rows: Vec<MemoryRow> = mem.rows().iter().cloned().collect();
+        assert_eq!(rows, reference.rows);
+    }
+}
+
+#[test]
+fn rebuilt_memory_matches_the_live_one() {
+    // What a checkpoint restore does: a fresh state fed the retained rows.
+    let mut live = MemoryState::new(7);
+    for t in 0..500u64 {
+        live.push(row(t));
+        let mut rebuilt = MemoryState::new(7);
+        live.rows().iter().for_each(|r| rebuilt.push(r.clone()));
+        assert_same(&rebuilt.features(t), &live.features(t), &format!("t {}", t));
+    }
+}
+
+#[test]
+fn large_windows_stay_cheap() {
+    // A 10^5-row window filled and then slid over by 10^5 more rows. Pushes
+    // and the ignite rate are O(1) throughout, and so are full reads while the
+    // window fills; after that each read rescans the window once.
+    let window = 100_000;
+    let mut mem = MemoryState::new(window);
+    for t in 0..window as u64 {
+        mem.push(row(t));
+        assert_eq!(mem.features(t).window_len, t as usize + 1);
+    }
+    let mut last = None;
+    for t in window as u64..2 * window as u64 {
+        mem.push(row(t));
+        assert!(mem.mem_ignite_rate() > 0.0);
+        if t % 10_000 == 0 || t == 2 * window as u64 - 1 {
+            last = Some(mem.features(t));
+        }
+    }
+    let f = last.unwrap();
+    assert_eq!(f.window_len, window);
+    assert_eq!(mem.window_max(), window);
+    assert_eq!(mem.rows().front().unwrap().t, window as u64);
+
+    let reference = Reference {
+        window_max: window,
+        rows: mem.rows().iter().cloned().collect(),
+    };
+    assert_same(&f, &reference.features(f.t, &mem), "after the slide");
+}
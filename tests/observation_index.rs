@@ -0,0 +1,55 @@
+use std::io::Cursor;
+
+use llm_nature_experiential::event::EventReader;
+use llm_nature_experiential::util::{ravel_multi_index, unravel_index, IndexError};
+
+#[test]
+fn ravel_and_unravel_round_trip() {
+    let shape = [3, 5];
+    for idx in 0..15 {
+        let o = unravel_index(idx, &shape).unwrap();
+        assert_eq!(ravel_multi_index(&o, &shape).unwrap(), idx);
+    }
+    assert_eq!(ravel_multi_index(&[1, 2], &shape).unwrap(), 7);
+    assert_eq!(unravel_index(7, &shape).unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn ravel_reports_axis_and_value() {
+    assert_eq!(
+        ravel_multi_index(&[1, 5], &[3, 5]),
+        Err(IndexError::OutOfBounds {
+            axis: 1,
+            value: 5,
+            dim: 5
+        })
+    );
+    assert_eq!(
+        ravel_multi_index(&[1], &[3, 5]),
+        Err(IndexError::RankMismatch {
+            expected: 2,
+            got: 1
+        })
+    );
+    assert_eq!(
+        unravel_index(15, &[3, 5]),
+        Err(IndexError::FlatOutOfBounds { idx: 15, size: 15 })
+    );
+}
+
+#[test]
+fn reader_rejects_bad_observation_with_line_number() {
+    let good = r#"{"t":0,"o":[1,2],"A_shape":[4,3,5],"A_flat_col":[0.2,0.6,0.1,0.1],"p_prior":[0.4,0.2,0.2,0.2],"task_vec":[0.0,1.0,0.0,0.0]}"#;
+    let bad = good.replace(r#""o":[1,2]"#, r#""o":[1,9]"#);
+    let input = format!("{}\n\n{}\n", good, bad);
+
+    let mut reader = EventReader::<_>::new(Cursor::new(input));
+    let (line, ev) = reader.next().unwrap().unwrap();
+    assert_eq!((line, ev.t), (1, 0));
+
+    let err = reader.next().unwrap().unwrap_err();
+    let msg = format!("{:#}", err);
+    assert!(msg.starts_with("line 3"), "{}", msg);
+    assert!(msg.contains("axis 1 has value 9"), "{}", msg);
+    assert!(reader.next().is_none());
+}
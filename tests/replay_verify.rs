@@ -0,0 +1,80 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::run_loop;
+
+fn verify(args: &[&str], ledger: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_replay_verify"))
+        .arg("--replay")
+        .arg(ledger)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn replay_ledger_reproduces_and_tampering_is_caught() {
+    let dir = run_loop("replay_verify", &[]);
+    let ledger = dir.join("replay_loop.ndjson");
+
+    let ok = verify(&[], &ledger);
+    assert!(
+        ok.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ok.stdout)
+    );
+    assert!(String::from_utf8_lossy(&ok.stdout).contains("OK: 3 steps"));
+
+    // A different kernel config must not reproduce the recorded ignition decisions.
+    let text = std::fs::read_to_string("config/default.toml").unwrap();
+    let cfg = dir.join("strict.toml");
+    std::fs::write(&cfg, text.replace("delta = 0.05", "delta = 50.0")).unwrap();
+    let changed = verify(&["--config", cfg.to_str().unwrap()], &ledger);
+    assert!(!changed.status.success());
+    let stdout = String::from_utf8_lossy(&changed.stdout);
+    assert!(stdout.contains("First divergent step: t=0"), "{}", stdout);
+    assert!(stdout.contains("ignite_reason"), "{}", stdout);
+
+    // Editing a recorded posterior is reported at that step.
+    let ledger_text = std::fs::read_to_string(&ledger).unwrap();
+    let mut lines: Vec<String> = ledger_text.lines().map(String::from).collect();
+    let mut row: serde_json::Value = serde_json::from_str(&lines[2]).unwrap();
+    row["q_after"][0] = serde_json::json!(0.5);
+    lines[2] = row.to_string();
+    let tampered = dir.join("tampered.ndjson");
+    std::fs::write(&tampered, lines.join("\n")).unwrap();
+    let bad = verify(&[], &tampered);
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stdout).contains("t=1 q_after"));
+}
+
+#[test]
+fn mismatched_vector_lengths_are_reported_not_fatal() {
+    let dir = run_loop("replay_verify_lengths", &[]);
+
+    let text = std::fs::read_to_string(dir.join("replay_loop.ndjson")).unwrap();
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let mut row: serde_json::Value = serde_json::from_str(&lines[2]).unwrap();
+    row["q_before"].as_array_mut().unwrap().pop();
+    lines[2] = row.to_string();
+    let truncated = dir.join("short_q_before.ndjson");
+    std::fs::write(&truncated, lines.join("\n")).unwrap();
+
+    let bad = verify(&["--all"], &truncated);
+    let stdout = String::from_utf8_lossy(&bad.stdout);
+    let stderr = String::from_utf8_lossy(&bad.stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    assert!(stdout.contains("First divergent step: t=1"), "{}", stdout);
+    assert!(
+        stdout.contains("t=1 prior: recorded length 4 replayed length 3 (q_before)"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stderr.contains("divergent step(s) in 3 checked"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use llm_nature_experiential::config::RunConfig;
+
+#[test]
+fn default_toml_matches_builtin_defaults() {
+    let cfg = RunConfig::load(Path::new("config/default.toml")).unwrap();
+    let builtin = RunConfig::default();
+    assert_eq!(
+        serde_json::to_value(&cfg).unwrap(),
+        serde_json::to_value(&builtin).unwrap()
+    );
+}
+
+#[test]
+fn json_partial_config_fills_defaults_and_validates() {
+    let cfg: RunConfig =
+        serde_json::from_str(r#"{"version":1,"kernel":{"params":{"gamma":0.5},"rg_cost":0.2}}"#)
+            .unwrap();
+    cfg.validate().unwrap();
+    assert_eq!(cfg.kernel.params.gamma, 0.5);
+    assert_eq!(cfg.kernel.params.alpha, 0.10);
+    assert_eq!(cfg.kernel.rg_cost, 0.2);
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let bad_gamma: RunConfig =
+        serde_json::from_str(r#"{"kernel":{"params":{"gamma":0.0}}}"#).unwrap();
+    assert!(bad_gamma.validate().is_err());
+
+    let bad_version: RunConfig = serde_json::from_str(r#"{"version":99}"#).unwrap();
+    assert!(bad_version.validate().is_err());
+
+    let deep_rg: RunConfig = serde_json::from_str(r#"{"kernel":{"rg_level":3}}"#).unwrap();
+    deep_rg.validate().unwrap();
+    assert!(deep_rg.kernel.validate_for_belief(4).is_err());
+    assert!(deep_rg.kernel.validate_for_belief(8).is_ok());
+
+    assert!(serde_json::from_str::<RunConfig>(r#"{"kernel":{"gama":0.5}}"#).is_err());
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::process::Command;
+
+use common::fresh_dir;
+use llm_nature_experiential::ledger::ReplayRow;
+
+#[test]
+fn sniff_run_evaluates_each_event_from_its_own_q_before() {
+    let dir = fresh_dir("sniff_run_batch");
+    let out = Command::new(env!("CARGO_BIN_EXE_sniff_run"))
+        .args(["--input", "data/sniff_events.ndjson", "--out-dir"])
+        .arg(&dir)
+        .output()
+        .expect("sniff_run failed to start");
+    assert!(out.status.success(), "nonzero exit status");
+
+    let text = std::fs::read_to_string(dir.join("replay.ndjson")).unwrap();
+    let rows: Vec<ReplayRow> = text
+        .lines()
+        .skip(1)
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].q_before, vec![0.35, 0.22, 0.25, 0.18]);
+    assert_eq!(rows[1].q_before, vec![0.33, 0.50, 0.08, 0.09]);
+    assert!(rows.iter().all(|r| r.mem_window_len == 1));
+}
+
+#[test]
+fn sniff_run_rejects_bad_events_by_line_and_leaves_no_partial_ledgers() {
+    let dir = fresh_dir("sniff_run_bad");
+    let good = std::fs::read_to_string("data/sniff_events.ndjson").unwrap();
+    let mut lines: Vec<String> = good.lines().map(String::from).collect();
+    lines[1] = lines[1].replace("0.33,0.50,0.08,0.09", "0.33,0.50,0.08");
+    let input = dir.join("short_q_before.ndjson");
+    std::fs::write(&input, lines.join("\n")).unwrap();
+
+    let out = Command::new(env!("CARGO_BIN_EXE_sniff_run"))
+        .arg("--input")
+        .arg(&input)
+        .arg("--out-dir")
+        .arg(&dir)
+        .output()
+        .expect("sniff_run failed to start");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(
+        stderr.contains("line 2: q0: length 3 does not match p_prior length 4"),
+        "{}",
+        stderr
+    );
+    let left: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|n| n.ends_with(".ndjson") || n.ends_with(".partial"))
+        .collect();
+    assert_eq!(left, vec!["short_q_before.ndjson".to_string()]);
+}